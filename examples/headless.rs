@@ -0,0 +1,57 @@
+//! Plays games with random inputs as fast as possible, no terminal needed.
+//!
+//! cargo run --release --no-default-features --example headless [games]
+
+use std::time::Instant;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use rustris::tetris_event::GameEvent;
+use rustris::tetris_input::{Action, Input};
+use rustris::tetris_model::{Ruleset, TetrisState, FRAMES_PER_SECOND};
+
+fn main() {
+    let games: u64 = std::env::args()
+        .nth(1)
+        .and_then(|games| games.parse().ok())
+        .unwrap_or(1000);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let start = Instant::now();
+    let (mut frames, mut pieces, mut lines) = (0, 0, 0);
+    for seed in 0..games {
+        let mut tetris_state = TetrisState::with_seed(1, Ruleset::default(), seed);
+        while !tetris_state.is_game_over() {
+            // Tap a random key every few frames
+            let inputs = if rng.random_ratio(1, 4) {
+                let action = Action::ALL[rng.random_range(0..Action::ALL.len())];
+                vec![Input::Press(action), Input::Release(action)]
+            } else {
+                Vec::new()
+            };
+            for event in tetris_state.step(&inputs) {
+                match event {
+                    GameEvent::Locked(_) => pieces += 1,
+                    GameEvent::LinesCleared { rows, .. } => lines += rows.len() as u64,
+                    _ => {}
+                }
+            }
+        }
+        frames += tetris_state.frames();
+    }
+    let seconds = start.elapsed().as_secs_f64();
+
+    println!(
+        "{} games, {} pieces, {} lines, {:.0} s of play",
+        games,
+        pieces,
+        lines,
+        frames as f64 / FRAMES_PER_SECOND as f64
+    );
+    println!(
+        "{:.3} s, {:.0} games/s, {:.0}x real time",
+        seconds,
+        games as f64 / seconds,
+        frames as f64 / FRAMES_PER_SECOND as f64 / seconds
+    );
+}
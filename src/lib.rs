@@ -0,0 +1,17 @@
+//! Tetris game engine without any user interface.
+//!
+//! [`tetris_model::TetrisState`] is a complete game, driven one frame at a
+//! time with [`tetris_model::TetrisState::tick`] and the player's
+//! [`tetris_input::Action`]s. Rules are picked with a
+//! [`tetris_model::Ruleset`], and a seeded game always plays out the same.
+
+#![warn(missing_docs)]
+
+pub mod tetris_event;
+mod tetris_format;
+pub mod tetris_input;
+pub mod tetris_model;
+pub mod tetris_randomizer;
+pub mod tetris_replay;
+pub mod tetris_rotation;
+pub mod tetris_save;
@@ -1,40 +1,271 @@
 mod tetris_gui;
-mod tetris_model;
+mod tetris_highscore;
+mod tetris_keys;
+mod tetris_save_slot;
+mod tetris_viewer;
 
-use std::{error::Error, io, time::Duration};
+use std::{
+    env,
+    error::Error,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent},
+    event::{
+        self, DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyEventKind, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 
-use tetris_model::TetrisState;
+use rustris::tetris_event::GameEvent;
+use rustris::tetris_input::{Action, Input};
+use rustris::tetris_model::{
+    GameMode, GravityCurve, LockReset, SoftDrop, TetrisState, FRAMES_PER_SECOND, MAX_PREVIEW,
+    MIN_PREVIEW,
+};
+use rustris::tetris_randomizer::RandomizerKind;
+use rustris::tetris_replay::Replay;
+use rustris::tetris_rotation::RotationSystemKind;
+use tetris_highscore::{HighScore, HighScores, MAX_NAME_LENGTH};
+use tetris_keys::{key_name, Command, KeyBindings, KeyPreset};
+use tetris_save_slot::{save_game, SaveSlot};
+use tetris_viewer::{load_replay, save_replay, ReplayViewer, LAST_REPLAY};
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum GameState {
-    Intro,
+    Intro(IntroItem),
     Game,
+    Paused(PauseItem),
+    GameOver(GameOverItem),
+    NameEntry,
+    HighScores(GameMode),
+    /// Key bindings screen with the selected command
+    Controls(Command),
+    /// Waiting for a key to add to the command
+    Rebind(Command),
+    /// Watching a replay
+    Replay,
     End,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum IntroItem {
+    /// Only shown when there is a saved game
+    Continue,
+    Mode,
+    Level,
+}
+
+impl IntroItem {
+    pub const ALL: [IntroItem; 3] = [IntroItem::Continue, IntroItem::Mode, IntroItem::Level];
+
+    /// The items on the intro screen
+    pub fn items(can_continue: bool) -> &'static [IntroItem] {
+        if can_continue {
+            &IntroItem::ALL
+        } else {
+            &IntroItem::ALL[1..]
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PauseItem {
+    Resume,
+    Restart,
+    Controls,
+    Quit,
+}
+
+impl PauseItem {
+    pub const ALL: [PauseItem; 4] = [
+        PauseItem::Resume,
+        PauseItem::Restart,
+        PauseItem::Controls,
+        PauseItem::Quit,
+    ];
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GameOverItem {
+    Retry,
+    Replay,
+    Menu,
+}
+
+impl GameOverItem {
+    pub const ALL: [GameOverItem; 3] = [
+        GameOverItem::Retry,
+        GameOverItem::Replay,
+        GameOverItem::Menu,
+    ];
+}
+
+/// The item before or after `current` in `items`, wrapping around
+fn cycle<T: Copy + PartialEq>(items: &[T], current: T, forward: bool) -> T {
+    let index = items.iter().position(|&item| item == current).unwrap_or(0);
+    let index = if forward {
+        (index + 1) % items.len()
+    } else {
+        (index + items.len() - 1) % items.len()
+    };
+    items[index]
+}
+
+/// `$XDG_DATA_HOME/rustris`, falling back to `~/.local/share/rustris`. High
+/// scores, replays and the saved game are kept there. A file that can not be
+/// written only loses what it would have kept, it never interrupts the game.
+pub fn data_dir() -> io::Result<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|data_home| data_home.join("rustris"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no home or data directory"))
+}
+
+/// Where to go when a game ends, a result good enough for the high score
+/// table asks for a name first
+fn game_over(
+    tetris_state: &mut TetrisState,
+    high_scores: &HighScores,
+    name: &mut String,
+) -> GameState {
+    tetris_state.release_all();
+    if high_scores.qualifies(tetris_state) {
+        name.clear();
+        GameState::NameEntry
+    } else {
+        GameState::GameOver(GameOverItem::Retry)
+    }
+}
+
+/// Keep the replay of a finished game as the last replay, and under its
+/// mode and score as well when it makes the high score table
+fn save_replays(recording: &Replay, tetris_state: &TetrisState, high_scores: &HighScores) {
+    let _ = save_replay(recording, LAST_REPLAY);
+    if high_scores.qualifies(tetris_state) {
+        let mode = tetris_state.ruleset().mode;
+        let name = format!("{}-{}", mode.id(), tetris_state.score());
+        let _ = save_replay(recording, &name);
+    }
+}
+
+/// Keep a game that is quit before it is over so it can be continued from
+/// the intro screen
+fn save_quit_game(tetris_state: &mut TetrisState, recording: &mut Replay) -> SaveSlot {
+    // Quit before its first frame, the recording is still the previous game
+    if tetris_state.frames() == 0 {
+        *recording = Replay::new(tetris_state);
+    }
+    // Held keys are let go, the same as when pausing
+    let releases = Action::ALL.map(Input::Release);
+    recording.record(tetris_state.frames(), &releases);
+    tetris_state.release_all();
+    let _ = save_game(tetris_state, recording);
+    SaveSlot::load()
+}
+
+/// Start a game at the chosen start level with the current ruleset
+fn start_game(tetris_state: &mut TetrisState, seed: Option<u64>) {
+    let level = tetris_state.start_level();
+    match seed {
+        Some(seed) => tetris_state.restart_with_seed(level, seed),
+        None => tetris_state.restart(level),
+    }
+}
+
+/// Fixed timestep clock, the game advances one frame every 1/FRAMES_PER_SECOND
+/// seconds regardless of how often input events arrive.
+struct GameClock {
+    frame_duration: Duration,
+    next_frame: Instant,
+}
+
+impl GameClock {
+    // Never try to catch up more than this many frames, e.g. after the process was suspended
+    const MAX_CATCH_UP_FRAMES: u32 = 10;
+
+    fn new() -> GameClock {
+        GameClock {
+            frame_duration: Duration::from_secs(1) / FRAMES_PER_SECOND,
+            next_frame: Instant::now(),
+        }
+    }
+    fn time_to_next_frame(&self) -> Duration {
+        self.next_frame.saturating_duration_since(Instant::now())
+    }
+    fn elapsed_frames(&mut self) -> u32 {
+        let now = Instant::now();
+        let mut frames = 0;
+        while self.next_frame <= now {
+            self.next_frame += self.frame_duration;
+            frames += 1;
+        }
+        if frames > GameClock::MAX_CATCH_UP_FRAMES {
+            self.next_frame = now + self.frame_duration;
+            frames = GameClock::MAX_CATCH_UP_FRAMES;
+        }
+        frames
+    }
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
+    // Optional `--seed <n>` to play a reproducible game
+    let seed = match std::env::args().skip_while(|arg| arg != "--seed").nth(1) {
+        Some(seed) => Some(seed.parse::<u64>()?),
+        None => None,
+    };
+    // Optional `--replay <file>` to watch a recorded game
+    let replay = match std::env::args().skip_while(|arg| arg != "--replay").nth(1) {
+        Some(path) => Some(load_replay(Path::new(&path))?),
+        None => None,
+    };
+
     // Terminal setup
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableFocusChange
+    )?;
+    // Ask for key release events where the terminal supports it, needed to know how long keys are held
+    let key_release_events = supports_keyboard_enhancement().unwrap_or(false);
+    if key_release_events {
+        execute!(
+            stdout,
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let res = run_app(&mut terminal);
+    let res = run_app(&mut terminal, seed, replay, key_release_events);
 
     // Restore terminal
+    if key_release_events {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableFocusChange
     )?;
     terminal.show_cursor()?;
 
@@ -45,71 +276,457 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn intro_state_control(key: KeyEvent, tetris_state: &mut TetrisState) -> GameState {
+fn intro_state_control(
+    key: KeyEvent,
+    tetris_state: &mut TetrisState,
+    selected: IntroItem,
+    save_slot: &mut SaveSlot,
+    recording: &mut Replay,
+    seed: Option<u64>,
+) -> GameState {
+    let mut ruleset = tetris_state.ruleset();
+    let items = IntroItem::items(save_slot.can_continue());
     match key.code {
-        KeyCode::Char(' ') => {
-            tetris_state.restart(0);
-            GameState::Game
+        KeyCode::Char(' ') | KeyCode::Enter => {
+            if selected == IntroItem::Continue {
+                if let Some(saved_game) = save_slot.take() {
+                    *tetris_state = saved_game.tetris_state;
+                    *recording = saved_game.recording;
+                    // Give the player a moment before the pieces fall again
+                    return GameState::Paused(PauseItem::Resume);
+                }
+            }
+            start_game(tetris_state, seed);
+            return GameState::Game;
+        }
+        KeyCode::Up => return GameState::Intro(cycle(items, selected, false)),
+        KeyCode::Down => return GameState::Intro(cycle(items, selected, true)),
+        KeyCode::Left | KeyCode::Right => {
+            let forward = key.code == KeyCode::Right;
+            match selected {
+                IntroItem::Continue => {}
+                IntroItem::Mode => ruleset = cycle(&GameMode::ALL, ruleset.mode, forward).ruleset(),
+                IntroItem::Level => {
+                    let level = tetris_state.start_level();
+                    tetris_state.set_start_level(if forward {
+                        level.saturating_add(1)
+                    } else {
+                        level.saturating_sub(1)
+                    });
+                }
+            }
+        }
+        KeyCode::Char('h') => return GameState::HighScores(ruleset.mode),
+        KeyCode::Char('k') => return GameState::Controls(Command::ALL[0]),
+        KeyCode::Char('q') => return GameState::End,
+        KeyCode::Char('g') => {
+            ruleset.gravity = match ruleset.gravity {
+                GravityCurve::Nes => GravityCurve::Guideline,
+                GravityCurve::Guideline => GravityCurve::Nes,
+            };
+        }
+        KeyCode::Char('p') => ruleset.ghost = !ruleset.ghost,
+        KeyCode::Char('n') => {
+            ruleset.preview = if ruleset.preview >= MAX_PREVIEW {
+                MIN_PREVIEW
+            } else {
+                ruleset.preview + 1
+            };
+        }
+        KeyCode::Char('r') => {
+            ruleset.randomizer = cycle(&RandomizerKind::ALL, ruleset.randomizer, true);
+        }
+        KeyCode::Char('o') => {
+            ruleset.rotation = cycle(&RotationSystemKind::ALL, ruleset.rotation, true);
         }
-        KeyCode::Char('q') => GameState::End,
-        _ => GameState::Intro,
+        KeyCode::Char('l') => {
+            ruleset.lock_reset = cycle(&LockReset::ALL, ruleset.lock_reset, true);
+        }
+        KeyCode::Char('d') => {
+            ruleset.soft_drop = match ruleset.soft_drop {
+                SoftDrop::Factor(_) => SoftDrop::Sonic,
+                SoftDrop::Sonic => SoftDrop::default(),
+            };
+        }
+        _ => {}
+    }
+    tetris_state.set_ruleset(ruleset);
+    GameState::Intro(selected)
+}
+
+/// Game keys are collected in `inputs` and played on the next frame, the
+/// same way `TetrisState::step` is used without a terminal
+fn game_state_control(
+    key: KeyEvent,
+    tetris_state: &mut TetrisState,
+    key_bindings: &KeyBindings,
+    key_release_events: bool,
+    inputs: &mut Vec<Input>,
+    seed: Option<u64>,
+) -> GameState {
+    let command = key_bindings.command(key.code);
+    if let Some(Command::Play(action)) = command {
+        match key.kind {
+            KeyEventKind::Press => {
+                inputs.push(Input::Press(action));
+                // Without release events every press is a tap and holding
+                // a key relies on the terminal's own key repeat
+                if !key_release_events {
+                    inputs.push(Input::Release(action));
+                }
+            }
+            KeyEventKind::Release => inputs.push(Input::Release(action)),
+            // Auto repeat is done by the game itself
+            KeyEventKind::Repeat => {}
+        }
+        return GameState::Game;
+    }
+
+    if key.kind != KeyEventKind::Press {
+        return GameState::Game;
+    }
+
+    match command {
+        Some(Command::Pause) => return pause(inputs),
+        Some(Command::Restart) => {
+            inputs.clear();
+            start_game(tetris_state, seed);
+        }
+        _ => {}
     }
+
+    GameState::Game
+}
+
+fn pause(inputs: &mut Vec<Input>) -> GameState {
+    // Key releases are not seen while paused, the held keys are let go on
+    // the first frame after it so replays see it too
+    inputs.extend(Action::ALL.map(Input::Release));
+    GameState::Paused(PauseItem::Resume)
 }
 
-fn game_state_control(key: KeyEvent, tetris_state: &mut TetrisState) -> GameState {
-    if key.code == KeyCode::Esc {
-        return GameState::Intro;
+fn pause_state_control(
+    key: KeyEvent,
+    tetris_state: &mut TetrisState,
+    key_bindings: &KeyBindings,
+    selected: PauseItem,
+    seed: Option<u64>,
+) -> GameState {
+    if key_bindings.command(key.code) == Some(Command::Pause) {
+        return GameState::Game;
     }
+    match key.code {
+        KeyCode::Up => GameState::Paused(cycle(&PauseItem::ALL, selected, false)),
+        KeyCode::Down => GameState::Paused(cycle(&PauseItem::ALL, selected, true)),
+        KeyCode::Esc => GameState::Game,
+        KeyCode::Enter | KeyCode::Char(' ') => match selected {
+            PauseItem::Resume => GameState::Game,
+            PauseItem::Restart => {
+                start_game(tetris_state, seed);
+                GameState::Game
+            }
+            PauseItem::Controls => GameState::Controls(Command::ALL[0]),
+            PauseItem::Quit => GameState::Intro(IntroItem::Mode),
+        },
+        _ => GameState::Paused(selected),
+    }
+}
 
+fn name_entry_control(
+    key: KeyEvent,
+    tetris_state: &TetrisState,
+    high_scores: &mut HighScores,
+    name: &mut String,
+) -> GameState {
     match key.code {
-        KeyCode::Up => {
-            tetris_state.rotate_cw();
+        KeyCode::Enter => {
+            let name = if name.trim().is_empty() {
+                "Player"
+            } else {
+                name.trim()
+            };
+            high_scores.insert(
+                tetris_state.ruleset().mode,
+                HighScore::new(name, tetris_state),
+            );
+            let _ = high_scores.save();
+            GameState::GameOver(GameOverItem::Retry)
         }
-        KeyCode::Down => {
-            tetris_state.rotate_ccw();
+        KeyCode::Esc => GameState::GameOver(GameOverItem::Retry),
+        KeyCode::Backspace => {
+            name.pop();
+            GameState::NameEntry
         }
-        KeyCode::Left => {
-            tetris_state.move_left();
+        KeyCode::Char(c) if !c.is_control() && name.chars().count() < MAX_NAME_LENGTH => {
+            name.push(c);
+            GameState::NameEntry
         }
-        KeyCode::Right => {
-            tetris_state.move_right();
+        _ => GameState::NameEntry,
+    }
+}
+
+fn high_scores_control(key: KeyEvent, mode: GameMode) -> GameState {
+    match key.code {
+        KeyCode::Left => GameState::HighScores(cycle(&GameMode::ALL, mode, false)),
+        KeyCode::Right => GameState::HighScores(cycle(&GameMode::ALL, mode, true)),
+        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('h') | KeyCode::Char('q') => {
+            GameState::Intro(IntroItem::Mode)
         }
-        KeyCode::Char(' ') => {
-            tetris_state.drop();
+        _ => GameState::HighScores(mode),
+    }
+}
+
+/// `back` is the screen the controls were opened from
+fn controls_control(
+    key: KeyEvent,
+    key_bindings: &mut KeyBindings,
+    selected: Command,
+    back: GameState,
+) -> GameState {
+    match key.code {
+        KeyCode::Up => GameState::Controls(cycle(&Command::ALL, selected, false)),
+        KeyCode::Down => GameState::Controls(cycle(&Command::ALL, selected, true)),
+        KeyCode::Left | KeyCode::Right => {
+            let forward = key.code == KeyCode::Right;
+            let preset = cycle(&KeyPreset::ALL, key_bindings.current_preset(), forward);
+            *key_bindings = KeyBindings::preset(preset);
+            GameState::Controls(selected)
         }
-        _ => {}
+        KeyCode::Enter => GameState::Rebind(selected),
+        KeyCode::Backspace | KeyCode::Delete => {
+            key_bindings.clear(selected);
+            GameState::Controls(selected)
+        }
+        KeyCode::Esc => {
+            let _ = key_bindings.save();
+            back
+        }
+        _ => GameState::Controls(selected),
     }
+}
 
-    GameState::Game
+fn rebind_control(key: KeyEvent, key_bindings: &mut KeyBindings, selected: Command) -> GameState {
+    // A key without a name could not be saved, keep waiting for another one
+    if key_name(key.code).is_none() {
+        return GameState::Rebind(selected);
+    }
+    // Esc always cancels, so it is never bound here
+    if key.code != KeyCode::Esc {
+        key_bindings.bind(selected, key.code);
+    }
+    GameState::Controls(selected)
 }
 
-fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>) -> Result<(), Box<dyn Error>> {
-    let mut game_state = GameState::Intro;
+fn game_over_state_control(
+    key: KeyEvent,
+    tetris_state: &mut TetrisState,
+    selected: GameOverItem,
+    recording: &Replay,
+    viewer: &mut Option<ReplayViewer>,
+    seed: Option<u64>,
+) -> GameState {
+    match key.code {
+        KeyCode::Up => GameState::GameOver(cycle(&GameOverItem::ALL, selected, false)),
+        KeyCode::Down => GameState::GameOver(cycle(&GameOverItem::ALL, selected, true)),
+        KeyCode::Esc => GameState::Intro(IntroItem::Mode),
+        KeyCode::Enter | KeyCode::Char(' ') => match selected {
+            GameOverItem::Retry => {
+                start_game(tetris_state, seed);
+                GameState::Game
+            }
+            GameOverItem::Replay => {
+                *viewer = Some(ReplayViewer::new(recording.clone()));
+                GameState::Replay
+            }
+            GameOverItem::Menu => GameState::Intro(IntroItem::Mode),
+        },
+        _ => GameState::GameOver(selected),
+    }
+}
+
+fn replay_control(
+    key: KeyEvent,
+    viewer: &mut ReplayViewer,
+    tetris_state: &TetrisState,
+) -> GameState {
+    match key.code {
+        KeyCode::Char(' ') | KeyCode::Char('p') => viewer.toggle_pause(),
+        KeyCode::Up | KeyCode::Char('+') => viewer.change_speed(true),
+        KeyCode::Down | KeyCode::Char('-') => viewer.change_speed(false),
+        KeyCode::Left => viewer.seek(false),
+        KeyCode::Right => viewer.seek(true),
+        KeyCode::Char(',') => viewer.step(false),
+        KeyCode::Char('.') => viewer.step(true),
+        KeyCode::Home => viewer.seek_to(0),
+        KeyCode::End => viewer.seek_to(u64::MAX),
+        KeyCode::Esc | KeyCode::Char('q') => {
+            // Back to the game the replay was opened from
+            return if tetris_state.is_game_over() {
+                GameState::GameOver(GameOverItem::Replay)
+            } else {
+                GameState::Intro(IntroItem::Mode)
+            };
+        }
+        _ => {}
+    }
+    GameState::Replay
+}
+
+fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    seed: Option<u64>,
+    replay: Option<Replay>,
+    key_release_events: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut save_slot = SaveSlot::load();
+    let mut game_state = GameState::Intro(if save_slot.can_continue() {
+        IntroItem::Continue
+    } else {
+        IntroItem::Mode
+    });
+    let mut viewer = replay.map(ReplayViewer::new);
+    if viewer.is_some() {
+        game_state = GameState::Replay;
+    }
 
     let mut tetris_state = TetrisState::new(0);
+    let mut recording = Replay::new(&tetris_state);
+    let mut clock = GameClock::new();
+    let mut high_scores = HighScores::load();
+    let mut name = String::new();
+    let mut key_bindings = KeyBindings::load();
+    let mut inputs = Vec::new();
+    let mut controls_back = GameState::Intro(IntroItem::Mode);
 
     loop {
         terminal.draw(|f| {
-            tetris_gui::draw(f, &game_state, &tetris_state);
+            tetris_gui::draw(
+                f,
+                &game_state,
+                &tetris_state,
+                viewer.as_ref(),
+                &high_scores,
+                &name,
+                &key_bindings,
+                &save_slot,
+            );
         })?;
 
-        if event::poll(Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                game_state = match game_state {
-                    GameState::Intro => intro_state_control(key, &mut tetris_state),
-                    GameState::Game => game_state_control(key, &mut tetris_state),
-                    _ => game_state,
+        let previous_state = game_state;
+        if event::poll(clock.time_to_next_frame())? {
+            match event::read()? {
+                Event::Key(key) => {
+                    game_state = match game_state {
+                        GameState::Intro(selected) if key.kind == KeyEventKind::Press => {
+                            intro_state_control(
+                                key,
+                                &mut tetris_state,
+                                selected,
+                                &mut save_slot,
+                                &mut recording,
+                                seed,
+                            )
+                        }
+                        GameState::Game => game_state_control(
+                            key,
+                            &mut tetris_state,
+                            &key_bindings,
+                            key_release_events,
+                            &mut inputs,
+                            seed,
+                        ),
+                        GameState::Paused(selected) if key.kind == KeyEventKind::Press => {
+                            pause_state_control(
+                                key,
+                                &mut tetris_state,
+                                &key_bindings,
+                                selected,
+                                seed,
+                            )
+                        }
+                        GameState::GameOver(selected) if key.kind == KeyEventKind::Press => {
+                            game_over_state_control(
+                                key,
+                                &mut tetris_state,
+                                selected,
+                                &recording,
+                                &mut viewer,
+                                seed,
+                            )
+                        }
+                        GameState::NameEntry if key.kind == KeyEventKind::Press => {
+                            name_entry_control(key, &tetris_state, &mut high_scores, &mut name)
+                        }
+                        GameState::HighScores(mode) if key.kind == KeyEventKind::Press => {
+                            high_scores_control(key, mode)
+                        }
+                        GameState::Controls(selected) if key.kind == KeyEventKind::Press => {
+                            controls_control(key, &mut key_bindings, selected, controls_back)
+                        }
+                        GameState::Rebind(selected) if key.kind == KeyEventKind::Press => {
+                            rebind_control(key, &mut key_bindings, selected)
+                        }
+                        GameState::Replay if key.kind == KeyEventKind::Press => {
+                            match viewer.as_mut() {
+                                Some(viewer) => replay_control(key, viewer, &tetris_state),
+                                None => GameState::Intro(IntroItem::Mode),
+                            }
+                        }
+                        _ => game_state,
+                    }
+                }
+                Event::FocusLost if game_state == GameState::Game => {
+                    game_state = pause(&mut inputs);
                 }
+                _ => {}
+            }
+        }
+        // The controls can be opened from the pause menu, the releases
+        // queued by the pause must still reach the game
+        if !matches!(
+            game_state,
+            GameState::Game | GameState::Paused(_) | GameState::Controls(_) | GameState::Rebind(_)
+        ) {
+            inputs.clear();
+        }
+        if matches!(game_state, GameState::Controls(_))
+            && !matches!(
+                previous_state,
+                GameState::Controls(_) | GameState::Rebind(_)
+            )
+        {
+            controls_back = previous_state;
+        }
+        if matches!(previous_state, GameState::Game | GameState::Paused(_))
+            && matches!(game_state, GameState::Intro(_) | GameState::End)
+            && !tetris_state.is_game_over()
+        {
+            save_slot = save_quit_game(&mut tetris_state, &mut recording);
+            if matches!(game_state, GameState::Intro(_)) && save_slot.can_continue() {
+                game_state = GameState::Intro(IntroItem::Continue);
             }
         }
 
-        if game_state == GameState::Game {
-            tetris_state.tick();
+        let frames = clock.elapsed_frames();
+        for _ in 0..frames {
+            if game_state == GameState::Game {
+                // Every game is recorded from its first frame
+                if tetris_state.frames() == 0 {
+                    recording = Replay::new(&tetris_state);
+                }
+                recording.record(tetris_state.frames(), &inputs);
+                let events = tetris_state.step(&inputs);
+                inputs.clear();
 
-            if tetris_state.is_game_over() {
-                game_state = GameState::Intro;
+                if events.contains(&GameEvent::TopOut) {
+                    save_replays(&recording, &tetris_state, &high_scores);
+                    game_state = game_over(&mut tetris_state, &high_scores, &mut name);
+                }
             }
         }
+        if let (GameState::Replay, Some(viewer)) = (game_state, viewer.as_mut()) {
+            viewer.advance(frames);
+        }
 
         if game_state == GameState::End {
             break;
@@ -0,0 +1,53 @@
+//! What happened in the game, for front-ends that animate, play sounds,
+//! keep statistics or send the game over the network.
+
+use crate::tetris_model::{CurrentPiece, LineClear, PieceRotation, PieceType, TSpin};
+
+/// Something that happened in the game. Events are collected until they are
+/// taken with `TetrisState::take_events` or returned by `TetrisState::step`.
+///
+/// A lock gives `Locked`, then `TSpin`, `LinesCleared`, `Combo` and `LevelUp`
+/// when they apply, and then `Spawned` for the next piece or `TopOut`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum GameEvent {
+    /// A new piece entered the playfield
+    Spawned(CurrentPiece),
+    /// The current piece moved, sideways moves are one column and drops may
+    /// be several rows at once
+    Moved {
+        /// Columns moved, negative to the left
+        dx: i8,
+        /// Rows moved down
+        dy: i8,
+    },
+    /// The current piece rotated
+    Rotated {
+        /// Rotation state before
+        from: PieceRotation,
+        /// Rotation state after
+        to: PieceRotation,
+        /// Index in the kick list of the rotation system, 0 is no kick
+        kick: usize,
+        /// How far the kick moved the piece, x right and y up
+        offset: (i8, i8),
+    },
+    /// The current piece locked into the playfield where it is given
+    Locked(CurrentPiece),
+    /// The locked piece was a T-spin
+    TSpin(TSpin),
+    /// Full rows were removed
+    LinesCleared {
+        /// Indices of the removed rows from the top, before removal
+        rows: Vec<usize>,
+        /// Scoring details of the clear
+        clear: LineClear,
+    },
+    /// Clears in a row, sent from the second consecutive clear on
+    Combo(u32),
+    /// The level went up to the given level
+    LevelUp(u8),
+    /// The given piece was put in the hold box
+    Held(PieceType),
+    /// The stack reached the top, the game is over
+    TopOut,
+}
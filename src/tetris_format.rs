@@ -0,0 +1,233 @@
+//! Names and `key value` lines shared by the replay and saved game formats.
+
+use std::fmt::Write;
+
+use crate::tetris_model::{
+    BlockColor, GameMode, GravityCurve, LockReset, PieceRotation, PieceType, Ruleset, SoftDrop,
+    TSpin, MAX_SOFT_DROP_FACTOR,
+};
+use crate::tetris_randomizer::RandomizerKind;
+use crate::tetris_rotation::RotationSystemKind;
+
+const GRAVITY_NAMES: [(GravityCurve, &str); 2] = [
+    (GravityCurve::Nes, "nes"),
+    (GravityCurve::Guideline, "guideline"),
+];
+const RANDOMIZER_NAMES: [(RandomizerKind, &str); 5] = [
+    (RandomizerKind::Random, "random"),
+    (RandomizerKind::Bag7, "bag7"),
+    (RandomizerKind::Bag14, "bag14"),
+    (RandomizerKind::Nes, "nes"),
+    (RandomizerKind::Tgm, "tgm"),
+];
+const ROTATION_NAMES: [(RotationSystemKind, &str); 3] = [
+    (RotationSystemKind::Srs, "srs"),
+    (RotationSystemKind::Ars, "ars"),
+    (RotationSystemKind::Nes, "nes"),
+];
+const LOCK_RESET_NAMES: [(LockReset, &str); 3] = [
+    (LockReset::Infinite, "infinite"),
+    (LockReset::MoveReset, "move"),
+    (LockReset::StepReset, "step"),
+];
+pub const PIECE_NAMES: [(PieceType, &str); 7] = [
+    (PieceType::I, "I"),
+    (PieceType::L, "L"),
+    (PieceType::J, "J"),
+    (PieceType::O, "O"),
+    (PieceType::S, "S"),
+    (PieceType::Z, "Z"),
+    (PieceType::T, "T"),
+];
+pub const ROTATION_STATE_NAMES: [(PieceRotation, &str); 4] = [
+    (PieceRotation::North, "N"),
+    (PieceRotation::East, "E"),
+    (PieceRotation::South, "S"),
+    (PieceRotation::West, "W"),
+];
+pub const TSPIN_NAMES: [(TSpin, &str); 2] = [(TSpin::Mini, "mini"), (TSpin::Full, "full")];
+/// One letter per playfield cell, `.` is empty
+pub const BLOCK_NAMES: [(BlockColor, &str); 8] = [
+    (BlockColor::Black, "."),
+    (BlockColor::Red, "R"),
+    (BlockColor::Blue, "B"),
+    (BlockColor::Yellow, "Y"),
+    (BlockColor::Green, "G"),
+    (BlockColor::Magenta, "M"),
+    (BlockColor::Cyan, "C"),
+    (BlockColor::Orange, "O"),
+];
+
+pub fn name_of<T: Copy + PartialEq>(names: &[(T, &'static str)], value: T) -> &'static str {
+    names
+        .iter()
+        .find(|&&(item, _)| item == value)
+        .map_or("?", |&(_, name)| name)
+}
+
+pub fn parse_name<T: Copy>(names: &[(T, &str)], name: &str) -> Option<T> {
+    names
+        .iter()
+        .find(|&&(_, item_name)| item_name == name)
+        .map(|&(item, _)| item)
+}
+
+/// Pieces as a string of their letters, `-` for none
+pub fn pieces_to_string(pieces: impl Iterator<Item = PieceType>) -> String {
+    let pieces: String = pieces.map(|piece| name_of(&PIECE_NAMES, piece)).collect();
+    if pieces.is_empty() {
+        "-".to_string()
+    } else {
+        pieces
+    }
+}
+
+pub fn parse_pieces(value: &str) -> Option<Vec<PieceType>> {
+    if value == "-" {
+        return Some(Vec::new());
+    }
+    value
+        .chars()
+        .map(|c| parse_name(&PIECE_NAMES, c.encode_utf8(&mut [0; 4])))
+        .collect()
+}
+
+/// Every ruleset setting as a `key value` line
+pub fn write_ruleset(text: &mut String, ruleset: &Ruleset) {
+    let soft_drop = match ruleset.soft_drop {
+        SoftDrop::Factor(factor) => factor.to_string(),
+        SoftDrop::Sonic => "sonic".to_string(),
+    };
+    let settings = [
+        ("mode", ruleset.mode.id().to_string()),
+        (
+            "gravity",
+            name_of(&GRAVITY_NAMES, ruleset.gravity).to_string(),
+        ),
+        (
+            "randomizer",
+            name_of(&RANDOMIZER_NAMES, ruleset.randomizer).to_string(),
+        ),
+        (
+            "rotation",
+            name_of(&ROTATION_NAMES, ruleset.rotation).to_string(),
+        ),
+        ("lock_delay", ruleset.lock_delay.to_string()),
+        (
+            "lock_reset",
+            name_of(&LOCK_RESET_NAMES, ruleset.lock_reset).to_string(),
+        ),
+        ("soft_drop", soft_drop),
+        ("ghost", ruleset.ghost.to_string()),
+        ("das", ruleset.das.to_string()),
+        ("arr", ruleset.arr.to_string()),
+        ("preview", ruleset.preview.to_string()),
+    ];
+    for (key, value) in settings {
+        let _ = writeln!(text, "{} {}", key, value);
+    }
+}
+
+/// Set the ruleset setting `key`, None for an unknown key or a bad value.
+/// The mode only names the preset, it does not change the other settings.
+pub fn set_ruleset(ruleset: &mut Ruleset, key: &str, value: &str) -> Option<()> {
+    match key {
+        "mode" => ruleset.mode = GameMode::from_id(value)?,
+        "gravity" => ruleset.gravity = parse_name(&GRAVITY_NAMES, value)?,
+        "randomizer" => ruleset.randomizer = parse_name(&RANDOMIZER_NAMES, value)?,
+        "rotation" => ruleset.rotation = parse_name(&ROTATION_NAMES, value)?,
+        "lock_delay" => ruleset.lock_delay = value.parse().ok()?,
+        "lock_reset" => ruleset.lock_reset = parse_name(&LOCK_RESET_NAMES, value)?,
+        "soft_drop" => {
+            ruleset.soft_drop = match value {
+                "sonic" => SoftDrop::Sonic,
+                _ => SoftDrop::Factor(
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|factor| (1..=MAX_SOFT_DROP_FACTOR).contains(factor))?,
+                ),
+            }
+        }
+        "ghost" => ruleset.ghost = value.parse().ok()?,
+        "das" => ruleset.das = value.parse().ok()?,
+        "arr" => ruleset.arr = value.parse().ok()?,
+        "preview" => ruleset.preview = value.parse().ok()?,
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Split the `key value` lines up to the line `end`, a line without a value
+/// is returned as the error
+pub fn read_settings<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    end: &str,
+) -> Result<Vec<(&'a str, &'a str)>, &'a str> {
+    let mut settings = Vec::new();
+    for line in lines {
+        if line.trim() == end {
+            break;
+        }
+        let (key, value) = line.trim().split_once(' ').ok_or(line)?;
+        settings.push((key, value.trim()));
+    }
+    Ok(settings)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::tetris_input::{Action, Input};
+    use crate::tetris_model::{Ruleset, TetrisState};
+    use crate::tetris_replay::{Replay, ReplayError, FILE_HEADER, REPLAY_VERSION};
+    use crate::tetris_save::{SaveError, SAVE_HEADER, SAVE_VERSION};
+
+    // Press a scrambled action every few frames and let go of it a little
+    // later, used by the replay and save tests. Hard drops are left out so
+    // pieces also slide and lock on the stack.
+    pub(crate) fn inputs(frame: u64) -> Vec<Input> {
+        let actions: Vec<Action> = Action::ALL
+            .into_iter()
+            .filter(|&action| action != Action::HardDrop)
+            .collect();
+        let scrambled = (frame / 5).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32;
+        let action = actions[scrambled as usize % actions.len()];
+        match frame % 5 {
+            0 => vec![Input::Press(action)],
+            3 => vec![Input::Release(action)],
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        let next_version = |text: String, header: &str, version: u32| {
+            text.replacen(
+                &format!("{} {}", header, version),
+                &format!("{} {}", header, version + 1),
+                1,
+            )
+        };
+        let state = TetrisState::with_seed(1, Ruleset::default(), 1);
+
+        let save = next_version(state.save(), SAVE_HEADER, SAVE_VERSION);
+        assert_eq!(
+            TetrisState::load(&save).err(),
+            Some(SaveError::Version(SAVE_VERSION + 1))
+        );
+        assert_eq!(
+            TetrisState::load("not a save").err(),
+            Some(SaveError::NotASave)
+        );
+
+        let replay = next_version(Replay::new(&state).to_string(), FILE_HEADER, REPLAY_VERSION);
+        assert_eq!(
+            replay.parse::<Replay>().err(),
+            Some(ReplayError::Version(REPLAY_VERSION + 1))
+        );
+        assert_eq!(
+            "not a replay".parse::<Replay>().err(),
+            Some(ReplayError::NotAReplay)
+        );
+    }
+}
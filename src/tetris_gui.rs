@@ -1,15 +1,28 @@
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph},
     Frame,
 };
 
-use crate::tetris_model::{BlockColor, Playfield, TetrisState};
+use crate::tetris_highscore::HighScores;
+use crate::tetris_keys::{key_name, Command, KeyBindings, KeyPreset};
+use crate::tetris_save_slot::SaveSlot;
+use crate::tetris_viewer::ReplayViewer;
+use crate::{GameOverItem, IntroItem, PauseItem};
+use rustris::tetris_input::Action;
+use rustris::tetris_model::{
+    BlockColor, GameMode, GravityCurve, LockReset, PieceGrid, Playfield, SoftDrop, TSpin,
+    TetrisState, FRAMES_PER_SECOND,
+};
+use rustris::tetris_randomizer::RandomizerKind;
+use rustris::tetris_rotation::RotationSystemKind;
 
 pub const FRAME_WIDTH: u16 = 80;
 pub const FRAME_HEIGHT: u16 = 24;
+// How long a T-spin or tetris stays announced
+const ANNOUNCE_FRAMES: u64 = 2 * FRAMES_PER_SECOND as u64;
 const BOARD_HEIGHT: u16 = Playfield::height() as u16 + 2; // playfield rows + 2 for borders
 
 fn from_block_color(block_color: BlockColor) -> Color {
@@ -25,35 +38,279 @@ fn from_block_color(block_color: BlockColor) -> Color {
     }
 }
 
-fn intro_field(f: &mut Frame, area: Rect) {
-    let content = "Press Space to Start!";
+/// Render a piece in its spawn orientation, either with one "██" per block
+/// or at half size with two block rows packed into each line using "▀▄".
+fn piece_lines(shape: PieceGrid, color: Color, full_size: bool) -> Vec<Line<'static>> {
+    let style = Style::default().fg(color);
+    let rows: Vec<_> = shape
+        .into_iter()
+        .filter(|row| row.iter().any(|&block| block))
+        .collect();
+
+    if full_size {
+        rows.iter()
+            .map(|row| {
+                let text: String = row
+                    .iter()
+                    .map(|&block| if block { "██" } else { "  " })
+                    .collect();
+                Line::styled(text.trim_end().to_string(), style)
+            })
+            .collect()
+    } else {
+        rows.chunks(2)
+            .map(|pair| {
+                let text: String = (0..pair[0].len())
+                    .map(|x| {
+                        let top = pair[0][x];
+                        let bottom = pair.len() > 1 && pair[1][x];
+                        match (top, bottom) {
+                            (true, true) => '█',
+                            (true, false) => '▀',
+                            (false, true) => '▄',
+                            (false, false) => ' ',
+                        }
+                    })
+                    .collect();
+                Line::styled(text.trim_end().to_string(), style)
+            })
+            .collect()
+    }
+}
+
+fn next_piece_height(tetris_state: &TetrisState) -> u16 {
+    // Borders, two lines for the first piece and a gap plus a line for each following piece
+    2 + 2 + 2 * (tetris_state.next_pieces().count() as u16).saturating_sub(1)
+}
+
+fn next_piece_lines(tetris_state: &TetrisState) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    for (i, piece) in tetris_state.next_pieces().enumerate() {
+        if i == 0 {
+            let color = from_block_color(piece.color());
+            let mut piece_lines = piece_lines(tetris_state.spawn_shape(piece), color, true);
+            piece_lines.resize(2, Line::default());
+            lines.extend(piece_lines);
+        } else {
+            lines.push(Line::default());
+            let color = from_block_color(piece.color());
+            lines.extend(piece_lines(tetris_state.spawn_shape(piece), color, false));
+        }
+    }
+    lines
+}
+
+fn hold_piece_lines(tetris_state: &TetrisState) -> Vec<Line<'static>> {
+    match tetris_state.held_piece() {
+        Some(piece) => {
+            // Grey out the held piece while it can't be swapped back
+            let color = if tetris_state.can_hold() {
+                from_block_color(piece.color())
+            } else {
+                Color::DarkGray
+            };
+            piece_lines(tetris_state.spawn_shape(piece), color, true)
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Blinking banner over the board after a perfect clear
+fn perfect_clear_banner(f: &mut Frame, board_area: Rect, tetris_state: &TetrisState) {
+    let Some(clear) = tetris_state.last_clear() else {
+        return;
+    };
+    let age = tetris_state.frames() - clear.frame;
+    let blink_frames = FRAMES_PER_SECOND as u64 / 4;
+    if !clear.perfect_clear || age >= ANNOUNCE_FRAMES || (age / blink_frames) % 2 == 1 {
+        return;
+    }
+    let banner_area = Rect::new(
+        board_area.x + 1,
+        board_area.y + board_area.height / 2 - 1,
+        board_area.width - 2,
+        1,
+    );
+    f.render_widget(Clear, banner_area);
+    f.render_widget(
+        Paragraph::new(" ALL CLEAR ")
+            .style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .alignment(Alignment::Center),
+        banner_area,
+    );
+}
 
-    let paragraph = Paragraph::new(content)
+fn announcement_lines(tetris_state: &TetrisState) -> Vec<Line<'static>> {
+    let Some(clear) = tetris_state.last_clear() else {
+        return Vec::new();
+    };
+    if tetris_state.frames() - clear.frame >= ANNOUNCE_FRAMES {
+        return Vec::new();
+    }
+    let kind = match clear.tspin {
+        Some(TSpin::Full) => "T-SPIN",
+        Some(TSpin::Mini) => "MINI T-SPIN",
+        None if clear.rows == 4 => "TETRIS",
+        None => return Vec::new(),
+    };
+    let kind = if clear.back_to_back {
+        format!("B2B {}", kind)
+    } else {
+        kind.to_string()
+    };
+    let rows = match (clear.tspin, clear.rows) {
+        (Some(_), 1) => "SINGLE",
+        (Some(_), 2) => "DOUBLE",
+        (Some(_), 3) => "TRIPLE",
+        _ => "",
+    };
+    vec![Line::from(kind), Line::from(rows)]
+}
+
+/// Start menu, the selected row is changed with the arrow keys and the
+/// remaining settings with their letter keys. A saved game is offered as
+/// the first row.
+fn intro_field(
+    f: &mut Frame,
+    area: Rect,
+    tetris_state: &TetrisState,
+    selected: IntroItem,
+    save_slot: &SaveSlot,
+) {
+    let gravity = match tetris_state.ruleset().gravity {
+        GravityCurve::Nes => "NES",
+        GravityCurve::Guideline => "Guideline",
+    };
+    let randomizer = match tetris_state.ruleset().randomizer {
+        RandomizerKind::Random => "Random",
+        RandomizerKind::Bag7 => "7-bag",
+        RandomizerKind::Bag14 => "14-bag",
+        RandomizerKind::Nes => "NES",
+        RandomizerKind::Tgm => "TGM",
+    };
+    let rotation = match tetris_state.ruleset().rotation {
+        RotationSystemKind::Srs => "SRS",
+        RotationSystemKind::Ars => "ARS",
+        RotationSystemKind::Nes => "NES",
+    };
+    let lock_reset = match tetris_state.ruleset().lock_reset {
+        LockReset::Infinite => "infinite",
+        LockReset::MoveReset => "move reset",
+        LockReset::StepReset => "step reset",
+    };
+    let lock_delay = match tetris_state.ruleset().lock_delay {
+        0 => "one gravity step".to_string(),
+        frames => format!("{} ms", frames * 1000 / FRAMES_PER_SECOND),
+    };
+    let soft_drop = match tetris_state.ruleset().soft_drop {
+        SoftDrop::Factor(factor) => format!("{}x", factor),
+        SoftDrop::Sonic => "sonic".to_string(),
+    };
+    let settings = format!(
+        "Gravity: {} (G to change)\n\
+         Preview: {} (N to change)\n\
+         Randomizer: {} (R to change)\n\
+         Rotation: {} (O to change)\n\
+         Lock delay: {}, {} (L to change)\n\
+         Soft drop: {} (D to change)\n\
+         Ghost piece: {} (P to change)",
+        gravity,
+        tetris_state.ruleset().preview,
+        randomizer,
+        rotation,
+        lock_delay,
+        lock_reset,
+        soft_drop,
+        if tetris_state.ruleset().ghost {
+            "on"
+        } else {
+            "off"
+        }
+    );
+
+    let mut lines = vec![Line::from("Press Space to Start!"), Line::default()];
+    for &item in IntroItem::items(save_slot.can_continue()) {
+        let label = match (item, save_slot) {
+            (IntroItem::Continue, SaveSlot::Game(saved_game)) => format!(
+                "Continue {} game, score {}",
+                saved_game.tetris_state.ruleset().mode.name(),
+                saved_game.tetris_state.score()
+            ),
+            (IntroItem::Continue, _) => "Continue".to_string(),
+            (IntroItem::Mode, _) if !tetris_state.ruleset().is_preset() => format!(
+                "Mode: < {} > custom, no high scores",
+                tetris_state.ruleset().mode.name()
+            ),
+            (IntroItem::Mode, _) => format!("Mode: < {} >", tetris_state.ruleset().mode.name()),
+            (IntroItem::Level, _) => format!("Level: < {} >", tetris_state.start_level()),
+        };
+        lines.push(menu_item_line(&label, item == selected));
+    }
+    if let SaveSlot::Unreadable(err) = save_slot {
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            format!("Saved game not loaded: {}", err),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    lines.push(Line::default());
+    lines.extend(settings.lines().map(|line| Line::from(line.to_string())));
+    lines.push(Line::default());
+    lines.push(Line::from("H for High Scores, K for Controls, Q to quit"));
+
+    let height = lines.len() as u16;
+    let paragraph = Paragraph::new(lines)
         .style(Style::default().fg(Color::Cyan))
         .alignment(Alignment::Center);
 
     let v_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(45),
-            Constraint::Length(1),
-            Constraint::Percentage(45),
+            Constraint::Min(0),
+            Constraint::Length(height),
+            Constraint::Min(0),
         ])
         .split(area);
 
     f.render_widget(paragraph, v_chunks[1]);
 }
 
-fn game_field(f: &mut Frame, area: Rect, tetris_state: &TetrisState) {
-    let level = tetris_state.level();
-    let score_content = format!("Level: {}\nLines: 0\nScore: 0", level);
+/// Screen areas of the game field that overlays are drawn on top of
+struct GameAreas {
+    board: Rect,
+    next_piece: Rect,
+}
+
+/// Draw the game screen and return its areas. With `hidden` set the board,
+/// hold and next pieces are left empty.
+fn game_field(f: &mut Frame, area: Rect, tetris_state: &TetrisState, hidden: bool) -> GameAreas {
+    let score_content = format!(
+        "Level: {}\nLines: {}\nScore: {}\nCombo: {}\nB2B:   {}",
+        tetris_state.level(),
+        tetris_state.lines(),
+        tetris_state.score(),
+        tetris_state.combo(),
+        tetris_state.back_to_back()
+    );
     let score_block = Block::default().borders(Borders::ALL).title("ScoreBoard");
     let score_paragraph = Paragraph::new(score_content)
         .block(score_block)
         .style(Style::default().fg(Color::Cyan));
 
     let next_piece_block = Block::default().borders(Borders::ALL).title("Next Piece");
-    let next_piece_paragraph = Paragraph::new("").block(next_piece_block);
+    let next_piece_lines = if hidden {
+        Vec::new()
+    } else {
+        next_piece_lines(tetris_state)
+    };
+    let next_piece_paragraph = Paragraph::new(next_piece_lines)
+        .block(next_piece_block)
+        .alignment(Alignment::Center);
 
     // Split area into: left logo panel, board, right side panel
     let h_chunks = Layout::default()
@@ -85,9 +342,9 @@ fn game_field(f: &mut Frame, area: Rect, tetris_state: &TetrisState) {
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(board_v_pad), // Match board top padding
-            Constraint::Length(6),           // Scoreboard height
+            Constraint::Length(7),           // Scoreboard height
             Constraint::Length(1),           // Gap
-            Constraint::Length(6),           // Next piece height
+            Constraint::Length(next_piece_height(tetris_state)), // Next piece height
             Constraint::Min(0),              // Bottom flex
         ])
         .split(h_chunks[2]);
@@ -114,7 +371,11 @@ fn game_field(f: &mut Frame, area: Rect, tetris_state: &TetrisState) {
     let paragraph = Paragraph::new("").block(block);
     f.render_widget(paragraph, board_area);
 
-    let field = tetris_state.field();
+    let field = if hidden {
+        Playfield::default()
+    } else {
+        tetris_state.field()
+    };
     let data = field.data();
 
     // Draw the actual cells inside the board_area
@@ -128,6 +389,23 @@ fn game_field(f: &mut Frame, area: Rect, tetris_state: &TetrisState) {
         }
     }
 
+    // Ghost piece, shaded in the current piece color on empty cells only
+    if tetris_state.ruleset().ghost && !hidden {
+        let ghost_style = Style::default()
+            .fg(from_block_color(
+                tetris_state.current_piece().piece().color(),
+            ))
+            .add_modifier(Modifier::DIM);
+        for (x, y) in tetris_state.ghost_blocks() {
+            if data[y][x] == BlockColor::Black {
+                let cell_x = board_area.x + 1 + 2 * x as u16;
+                let cell_y = board_area.y + 1 + y as u16;
+                let cell = Paragraph::new("░░").style(ghost_style);
+                f.render_widget(cell, Rect::new(cell_x, cell_y, 2, 1));
+            }
+        }
+    }
+
     // Render scoreboard and next piece
     f.render_widget(score_paragraph, score_area);
     f.render_widget(next_piece_paragraph, next_piece_area);
@@ -166,7 +444,11 @@ fn game_field(f: &mut Frame, area: Rect, tetris_state: &TetrisState) {
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(0),
-            Constraint::Length(6),
+            Constraint::Length(6), // Logo height
+            Constraint::Length(1), // Gap
+            Constraint::Length(4), // Hold piece height
+            Constraint::Length(1), // Gap
+            Constraint::Length(2), // Announcement height
             Constraint::Min(0),
         ])
         .split(h_chunks[0]);
@@ -181,11 +463,353 @@ fn game_field(f: &mut Frame, area: Rect, tetris_state: &TetrisState) {
         .split(logo_v_chunks[1]);
 
     f.render_widget(logo_paragraph, logo_h_chunks[1]);
+
+    // Hold piece box, right aligned next to the board
+    let hold_area = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Min(0),     // Left flex
+            Constraint::Length(18), // Hold box width
+            Constraint::Length(1),  // Gap to the board
+        ])
+        .split(logo_v_chunks[3])[1];
+
+    let hold_block = Block::default().borders(Borders::ALL).title("Hold");
+    let hold_lines = if hidden {
+        Vec::new()
+    } else {
+        hold_piece_lines(tetris_state)
+    };
+    let hold_paragraph = Paragraph::new(hold_lines)
+        .block(hold_block)
+        .alignment(Alignment::Center);
+    f.render_widget(hold_paragraph, hold_area);
+
+    // Recent T-spin or tetris, centered under the hold box
+    if !hidden {
+        let announce_area = Rect {
+            y: logo_v_chunks[5].y,
+            height: logo_v_chunks[5].height,
+            ..hold_area
+        };
+        let announce_paragraph = Paragraph::new(announcement_lines(tetris_state))
+            .style(
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .alignment(Alignment::Center);
+        f.render_widget(announce_paragraph, announce_area);
+    }
+
+    GameAreas {
+        board: board_area,
+        next_piece: next_piece_area,
+    }
+}
+
+fn menu_item_line(label: &str, selected: bool) -> Line<'static> {
+    let style = if selected {
+        Style::default().fg(Color::Black).bg(Color::Cyan)
+    } else {
+        Style::default().fg(Color::Cyan)
+    };
+    Line::styled(format!(" {} ", label), style)
+}
+
+fn pause_menu(f: &mut Frame, board_area: Rect, selected: PauseItem) {
+    let mut lines = vec![
+        Line::styled("PAUSED", Style::default().fg(Color::Yellow)),
+        Line::default(),
+    ];
+    for item in PauseItem::ALL {
+        let label = match item {
+            PauseItem::Resume => "Resume",
+            PauseItem::Restart => "Restart",
+            PauseItem::Controls => "Controls",
+            PauseItem::Quit => "Quit to title",
+        };
+        lines.push(menu_item_line(label, item == selected));
+    }
+
+    let height = lines.len() as u16;
+    let menu_area = Rect::new(
+        board_area.x + 1,
+        board_area.y + (board_area.height - height) / 2,
+        board_area.width - 2,
+        height,
+    );
+    f.render_widget(Clear, menu_area);
+    f.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center),
+        menu_area,
+    );
+}
+
+/// Prompt for the name to put on the high score table, drawn over the board
+fn name_entry(f: &mut Frame, board_area: Rect, name: &str) {
+    let lines = vec![
+        Line::styled("NEW HIGH SCORE", Style::default().fg(Color::Yellow)),
+        Line::default(),
+        Line::styled("Enter your name", Style::default().fg(Color::Cyan)),
+        Line::styled(
+            format!("{}_", name),
+            Style::default().fg(Color::Black).bg(Color::Cyan),
+        ),
+    ];
+
+    let height = lines.len() as u16;
+    let entry_area = Rect::new(
+        board_area.x + 1,
+        board_area.y + (board_area.height - height) / 2,
+        board_area.width - 2,
+        height,
+    );
+    f.render_widget(Clear, entry_area);
+    f.render_widget(
+        Paragraph::new(lines).alignment(Alignment::Center),
+        entry_area,
+    );
+}
+
+/// Game time as minutes, seconds and hundredths
+fn format_time(frames: u64) -> String {
+    let frames_per_second = FRAMES_PER_SECOND as u64;
+    let seconds = frames / frames_per_second;
+    let hundredths = frames % frames_per_second * 100 / frames_per_second;
+    format!("{}:{:02}.{:02}", seconds / 60, seconds % 60, hundredths)
+}
+
+fn high_scores_field(f: &mut Frame, area: Rect, high_scores: &HighScores, mode: GameMode) {
+    let mut lines = vec![
+        Line::styled(
+            format!("High Scores - {}", mode.name()),
+            Style::default().fg(Color::Yellow),
+        ),
+        Line::default(),
+        Line::from(format!(
+            "{:>2}  {:<12} {:>8} {:>5} {:>5} {:>9}  {:<10}",
+            "#", "Name", "Score", "Lines", "Level", "Time", "Date"
+        )),
+    ];
+    let entries = high_scores.entries(mode);
+    for (rank, entry) in entries.iter().enumerate() {
+        lines.push(Line::from(format!(
+            "{:>2}  {:<12} {:>8} {:>5} {:>5} {:>9}  {:<10}",
+            rank + 1,
+            entry.name,
+            entry.score,
+            entry.lines,
+            entry.level,
+            format_time(entry.frames),
+            entry.date
+        )));
+    }
+    if entries.is_empty() {
+        lines.push(Line::from("No scores yet"));
+    }
+    lines.push(Line::default());
+    lines.push(Line::from("Left/Right to change mode, Esc to return"));
+
+    let height = lines.len() as u16;
+    let v_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(height),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    f.render_widget(
+        Paragraph::new(lines)
+            .style(Style::default().fg(Color::Cyan))
+            .alignment(Alignment::Center),
+        v_chunks[1],
+    );
+}
+
+/// Key bindings for every command, `waiting` marks the selected command as
+/// waiting for a new key
+fn controls_field(
+    f: &mut Frame,
+    area: Rect,
+    key_bindings: &KeyBindings,
+    selected: Command,
+    waiting: bool,
+) {
+    let preset = match key_bindings.current_preset() {
+        KeyPreset::Arrows => "Arrow keys",
+        KeyPreset::Wasd => "WASD",
+        KeyPreset::Vim => "Vim",
+    };
+    let mut lines = vec![
+        Line::styled("Controls", Style::default().fg(Color::Yellow)),
+        Line::default(),
+        Line::from(format!("Preset: < {} >", preset)),
+        Line::default(),
+    ];
+    for command in Command::ALL {
+        let label = match command {
+            Command::Play(Action::MoveLeft) => "Move left",
+            Command::Play(Action::MoveRight) => "Move right",
+            Command::Play(Action::SoftDrop) => "Soft drop",
+            Command::Play(Action::HardDrop) => "Hard drop",
+            Command::Play(Action::RotateCw) => "Rotate right",
+            Command::Play(Action::RotateCcw) => "Rotate left",
+            Command::Play(Action::Rotate180) => "Rotate 180",
+            Command::Play(Action::Hold) => "Hold",
+            Command::Pause => "Pause",
+            Command::Restart => "Restart",
+        };
+        let keys = if waiting && command == selected {
+            "press a key".to_string()
+        } else {
+            let names: Vec<String> = key_bindings
+                .keys(command)
+                .iter()
+                .filter_map(|&code| key_name(code))
+                .collect();
+            names.join(", ")
+        };
+        lines.push(menu_item_line(
+            &format!("{:<12} {:<16}", label, keys),
+            command == selected,
+        ));
+    }
+    lines.push(Line::default());
+    if waiting {
+        lines.push(Line::from("Esc to cancel, it can not be bound here"));
+        lines.push(Line::from("Pause always keeps one key"));
+    } else {
+        lines.push(Line::from(
+            "Enter to add a key, Backspace to clear, Left/Right for presets",
+        ));
+        lines.push(Line::from("Esc to save and return"));
+    }
+
+    let height = lines.len() as u16;
+    let v_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(0),
+            Constraint::Length(height),
+            Constraint::Min(0),
+        ])
+        .split(area);
+
+    f.render_widget(
+        Paragraph::new(lines)
+            .style(Style::default().fg(Color::Cyan))
+            .alignment(Alignment::Center),
+        v_chunks[1],
+    );
+}
+
+/// Final statistics and the game over menu, drawn over the next piece box so
+/// the topped-out board stays visible
+fn game_over_panel(
+    f: &mut Frame,
+    next_piece_area: Rect,
+    tetris_state: &TetrisState,
+    selected: GameOverItem,
+) {
+    let stats = [
+        format!("Score:  {}", tetris_state.score()),
+        format!("Lines:  {}", tetris_state.lines()),
+        format!("Level:  {}", tetris_state.level()),
+        format!("Time:   {}", format_time(tetris_state.frames())),
+        format!("Pieces: {}", tetris_state.pieces_placed()),
+        format!("PPS:    {:.2}", tetris_state.pieces_per_second()),
+        format!("PCs:    {}", tetris_state.perfect_clears()),
+    ];
+    let mut lines: Vec<Line> = stats.into_iter().map(Line::from).collect();
+    lines.push(Line::default());
+    for item in GameOverItem::ALL {
+        let label = match item {
+            GameOverItem::Retry => "Retry",
+            GameOverItem::Replay => "Replay",
+            GameOverItem::Menu => "Menu",
+        };
+        lines.push(menu_item_line(label, item == selected).alignment(Alignment::Center));
+    }
+
+    let area = Rect::new(
+        next_piece_area.x,
+        next_piece_area.y,
+        next_piece_area.width,
+        lines.len() as u16 + 2,
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled("GAME OVER", Style::default().fg(Color::Red)));
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .style(Style::default().fg(Color::Cyan)),
+        area,
+    );
+}
+
+/// Replay controls and position, drawn over the next piece box
+fn replay_panel(f: &mut Frame, next_piece_area: Rect, viewer: &ReplayViewer) {
+    let status = if viewer.is_at_end() && viewer.is_paused() {
+        "End".to_string()
+    } else if viewer.is_paused() {
+        "Paused".to_string()
+    } else {
+        format!("Playing {}x", viewer.speed())
+    };
+    let help = [
+        "Space  play",
+        "Up/Dn  speed",
+        "Lt/Rt  seek",
+        ", .    frame",
+        "Esc    back",
+    ];
+    let mut lines = vec![
+        Line::from(status),
+        Line::from(format_time(viewer.frame())),
+        Line::from(format!("/ {}", format_time(viewer.length()))),
+        Line::default(),
+    ];
+    lines.extend(
+        help.into_iter()
+            .map(|line| Line::styled(line, Style::default().fg(Color::DarkGray))),
+    );
+
+    let area = Rect::new(
+        next_piece_area.x,
+        next_piece_area.y,
+        next_piece_area.width,
+        lines.len() as u16 + 2,
+    );
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(Span::styled("REPLAY", Style::default().fg(Color::Yellow)));
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines)
+            .block(block)
+            .style(Style::default().fg(Color::Cyan)),
+        area,
+    );
 }
 
 /// Main draw function called from the game loop.
 /// Handles size checking, outer frame, and dispatching to intro/game rendering.
-pub fn draw(f: &mut Frame, game_state: &super::GameState, tetris_state: &TetrisState) {
+#[allow(clippy::too_many_arguments)]
+pub fn draw(
+    f: &mut Frame,
+    game_state: &super::GameState,
+    tetris_state: &TetrisState,
+    viewer: Option<&ReplayViewer>,
+    high_scores: &HighScores,
+    name: &str,
+    key_bindings: &KeyBindings,
+    save_slot: &SaveSlot,
+) {
     let size = f.size();
 
     // Check if terminal is too small
@@ -229,8 +853,39 @@ pub fn draw(f: &mut Frame, game_state: &super::GameState, tetris_state: &TetrisS
     });
 
     match game_state {
-        super::GameState::Intro => intro_field(f, inner_area),
-        super::GameState::Game => game_field(f, inner_area, tetris_state),
+        super::GameState::Intro(selected) => {
+            intro_field(f, inner_area, tetris_state, *selected, save_slot)
+        }
+        super::GameState::Game => {
+            let areas = game_field(f, inner_area, tetris_state, false);
+            perfect_clear_banner(f, areas.board, tetris_state);
+        }
+        super::GameState::Paused(selected) => {
+            let areas = game_field(f, inner_area, tetris_state, true);
+            pause_menu(f, areas.board, *selected);
+        }
+        super::GameState::GameOver(selected) => {
+            let areas = game_field(f, inner_area, tetris_state, false);
+            game_over_panel(f, areas.next_piece, tetris_state, *selected);
+        }
+        super::GameState::NameEntry => {
+            let areas = game_field(f, inner_area, tetris_state, false);
+            name_entry(f, areas.board, name);
+        }
+        super::GameState::HighScores(mode) => high_scores_field(f, inner_area, high_scores, *mode),
+        super::GameState::Controls(selected) => {
+            controls_field(f, inner_area, key_bindings, *selected, false)
+        }
+        super::GameState::Rebind(selected) => {
+            controls_field(f, inner_area, key_bindings, *selected, true)
+        }
+        super::GameState::Replay => {
+            if let Some(viewer) = viewer {
+                let areas = game_field(f, inner_area, viewer.state(), false);
+                perfect_clear_banner(f, areas.board, viewer.state());
+                replay_panel(f, areas.next_piece, viewer);
+            }
+        }
         _ => {}
     }
 }
@@ -0,0 +1,172 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::data_dir;
+use rustris::tetris_model::{GameMode, TetrisState};
+
+/// Number of results kept for each game mode
+pub const MAX_HIGH_SCORES: usize = 10;
+pub const MAX_NAME_LENGTH: usize = 12;
+
+const FILE_NAME: &str = "highscores.txt";
+const FILE_HEADER: &str = "rustris highscores 1";
+
+#[derive(Clone)]
+pub struct HighScore {
+    pub name: String,
+    pub score: u32,
+    pub lines: u32,
+    pub level: u8,
+    /// Game duration in frames
+    pub frames: u64,
+    /// Date the game was played as YYYY-MM-DD
+    pub date: String,
+}
+
+impl HighScore {
+    /// The result of a finished game, dated today
+    pub fn new(name: &str, tetris_state: &TetrisState) -> HighScore {
+        HighScore {
+            name: name.to_string(),
+            score: tetris_state.score(),
+            lines: tetris_state.lines(),
+            level: tetris_state.level(),
+            frames: tetris_state.frames(),
+            date: today(),
+        }
+    }
+
+    fn to_line(&self, mode: &str) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            mode, self.name, self.score, self.lines, self.level, self.frames, self.date
+        )
+    }
+
+    fn from_line(line: &str) -> Option<(String, HighScore)> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [mode, name, score, lines, level, frames, date] = fields[..] else {
+            return None;
+        };
+        let high_score = HighScore {
+            name: name.to_string(),
+            score: score.parse().ok()?,
+            lines: lines.parse().ok()?,
+            level: level.parse().ok()?,
+            frames: frames.parse().ok()?,
+            date: date.to_string(),
+        };
+        Some((mode.to_string(), high_score))
+    }
+}
+
+/// The best results for each game mode, stored as a tab separated text file
+/// in the XDG data directory
+#[derive(Default)]
+pub struct HighScores {
+    modes: BTreeMap<String, Vec<HighScore>>,
+}
+
+impl HighScores {
+    /// Read the high score file, a missing or unreadable file gives an empty
+    /// table and malformed lines are skipped
+    pub fn load() -> HighScores {
+        let mut high_scores = HighScores::default();
+        let Ok(content) = file_path().and_then(fs::read_to_string) else {
+            return high_scores;
+        };
+        let mut lines = content.lines();
+        if lines.next() != Some(FILE_HEADER) {
+            return high_scores;
+        }
+        for (mode, high_score) in lines.filter_map(HighScore::from_line) {
+            // Older files name the mode by its display name
+            let mode = GameMode::ALL
+                .into_iter()
+                .find(|game_mode| game_mode.name() == mode)
+                .map_or(mode, |game_mode| game_mode.id().to_string());
+            high_scores.modes.entry(mode).or_default().push(high_score);
+        }
+        for entries in high_scores.modes.values_mut() {
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+            entries.truncate(MAX_HIGH_SCORES);
+        }
+        high_scores
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = file_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut content = format!("{}\n", FILE_HEADER);
+        for (mode, entries) in &self.modes {
+            for high_score in entries {
+                content.push_str(&high_score.to_line(mode));
+                content.push('\n');
+            }
+        }
+        fs::write(path, content)
+    }
+
+    pub fn entries(&self, mode: GameMode) -> &[HighScore] {
+        self.modes.get(mode.id()).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether a finished game makes it onto the table of its mode, games
+    /// with changed rules are left out
+    pub fn qualifies(&self, tetris_state: &TetrisState) -> bool {
+        let ruleset = tetris_state.ruleset();
+        let entries = self.entries(ruleset.mode);
+        let score = tetris_state.score();
+        ruleset.is_preset()
+            && score > 0
+            && (entries.len() < MAX_HIGH_SCORES || entries.iter().any(|entry| score > entry.score))
+    }
+
+    /// Add a result, ties rank below earlier results
+    pub fn insert(&mut self, mode: GameMode, mut high_score: HighScore) {
+        high_score.name = high_score
+            .name
+            .chars()
+            .filter(|c| !c.is_control())
+            .take(MAX_NAME_LENGTH)
+            .collect();
+        let entries = self.modes.entry(mode.id().to_string()).or_default();
+        let rank = entries.partition_point(|entry| entry.score >= high_score.score);
+        entries.insert(rank, high_score);
+        entries.truncate(MAX_HIGH_SCORES);
+    }
+}
+
+/// `highscores.txt` in the data directory
+fn file_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join(FILE_NAME))
+}
+
+/// Today's UTC date as YYYY-MM-DD
+fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (year, month, day) = civil_from_days((seconds / 86400) as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Gregorian date of a day count since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
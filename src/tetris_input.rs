@@ -0,0 +1,189 @@
+//! Player actions and the held key state behind auto shift.
+
+/// Player actions understood by the game
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    /// Shift one column left, repeats after DAS while held
+    MoveLeft,
+    /// Shift one column right, repeats after DAS while held
+    MoveRight,
+    /// Fall faster while held
+    SoftDrop,
+    /// Drop to the bottom and lock at once
+    HardDrop,
+    /// Rotate clockwise
+    RotateCw,
+    /// Rotate counterclockwise
+    RotateCcw,
+    /// Rotate half a turn
+    Rotate180,
+    /// Swap with the held piece
+    Hold,
+}
+
+impl Action {
+    /// Every action
+    pub const ALL: [Action; 8] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateCw,
+        Action::RotateCcw,
+        Action::Rotate180,
+        Action::Hold,
+    ];
+}
+
+/// A key going down or up during one frame of `TetrisState::step`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Input {
+    /// Same as `TetrisState::press`
+    Press(Action),
+    /// Same as `TetrisState::release`
+    Release(Action),
+}
+
+/// Horizontal direction of a shift
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    /// Towards column 0
+    Left,
+    /// Towards the last column
+    Right,
+}
+
+/// Keeps track of held actions for Delayed Auto Shift and held soft drop.
+/// The horizontal direction pressed last wins while both are held.
+#[derive(Default)]
+pub struct InputHandler {
+    left_held: bool,
+    right_held: bool,
+    soft_drop_held: bool,
+    direction: Option<Direction>,
+    das_timer: u32,
+}
+
+impl InputHandler {
+    /// An action key went down
+    pub fn press(&mut self, action: Action) {
+        match action {
+            Action::MoveLeft => {
+                self.left_held = true;
+                self.shift_direction(Some(Direction::Left));
+            }
+            Action::MoveRight => {
+                self.right_held = true;
+                self.shift_direction(Some(Direction::Right));
+            }
+            Action::SoftDrop => self.soft_drop_held = true,
+            _ => {}
+        }
+    }
+    /// An action key went up
+    pub fn release(&mut self, action: Action) {
+        match action {
+            Action::MoveLeft => {
+                self.left_held = false;
+                if self.direction == Some(Direction::Left) {
+                    self.shift_direction(self.right_held.then_some(Direction::Right));
+                }
+            }
+            Action::MoveRight => {
+                self.right_held = false;
+                if self.direction == Some(Direction::Right) {
+                    self.shift_direction(self.left_held.then_some(Direction::Left));
+                }
+            }
+            Action::SoftDrop => self.soft_drop_held = false,
+            _ => {}
+        }
+    }
+    fn shift_direction(&mut self, direction: Option<Direction>) {
+        self.direction = direction;
+        self.das_timer = 0;
+    }
+    /// Whether soft drop is held down
+    pub fn soft_drop_held(&self) -> bool {
+        self.soft_drop_held
+    }
+    /// Advance one frame and return the direction and number of cells to
+    /// auto shift, an ARR of 0 shifts as far as possible
+    pub fn auto_shift(&mut self, das: u32, arr: u32) -> Option<(Direction, u32)> {
+        let direction = self.direction?;
+        self.das_timer = self.das_timer.saturating_add(1);
+        if self.das_timer < das {
+            return None;
+        }
+        if arr == 0 {
+            Some((direction, u32::MAX))
+        } else if (self.das_timer - das).is_multiple_of(arr) {
+            Some((direction, 1))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Frames, counted from 1 after the press, that auto shift and how far
+    fn shifts(
+        input: &mut InputHandler,
+        das: u32,
+        arr: u32,
+        frames: u32,
+    ) -> Vec<(u32, Direction, u32)> {
+        (1..=frames)
+            .filter_map(|frame| {
+                input
+                    .auto_shift(das, arr)
+                    .map(|(direction, cells)| (frame, direction, cells))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn das_then_arr() {
+        let mut input = InputHandler::default();
+        input.press(Action::MoveRight);
+        assert_eq!(
+            shifts(&mut input, 10, 3, 17),
+            [
+                (10, Direction::Right, 1),
+                (13, Direction::Right, 1),
+                (16, Direction::Right, 1),
+            ]
+        );
+        input.release(Action::MoveRight);
+        assert_eq!(shifts(&mut input, 10, 3, 20), []);
+    }
+
+    #[test]
+    fn arr_0_shifts_to_the_wall() {
+        let mut input = InputHandler::default();
+        input.press(Action::MoveLeft);
+        let shifts = shifts(&mut input, 10, 0, 12);
+        assert_eq!(shifts[0], (10, Direction::Left, u32::MAX));
+        assert_eq!(shifts.len(), 3);
+    }
+
+    #[test]
+    fn last_pressed_direction_wins() {
+        let mut input = InputHandler::default();
+        input.press(Action::MoveLeft);
+        assert_eq!(shifts(&mut input, 10, 3, 10), [(10, Direction::Left, 1)]);
+        // The new direction waits for its own DAS
+        input.press(Action::MoveRight);
+        assert_eq!(shifts(&mut input, 10, 3, 10), [(10, Direction::Right, 1)]);
+        // Letting go of it goes back to the direction still held
+        input.release(Action::MoveRight);
+        assert_eq!(shifts(&mut input, 10, 3, 10), [(10, Direction::Left, 1)]);
+        // Releasing the direction that is not shifting changes nothing
+        input.press(Action::MoveRight);
+        input.release(Action::MoveLeft);
+        assert_eq!(shifts(&mut input, 10, 3, 10), [(10, Direction::Right, 1)]);
+    }
+}
@@ -0,0 +1,333 @@
+use std::{env, fs, io, path::PathBuf};
+
+use crossterm::event::KeyCode;
+
+use rustris::tetris_input::Action;
+
+const FILE_NAME: &str = "keys.toml";
+
+/// Everything a key can be bound to while playing
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+    Play(Action),
+    Pause,
+    Restart,
+}
+
+impl Command {
+    pub const ALL: [Command; 10] = [
+        Command::Play(Action::MoveLeft),
+        Command::Play(Action::MoveRight),
+        Command::Play(Action::SoftDrop),
+        Command::Play(Action::HardDrop),
+        Command::Play(Action::RotateCw),
+        Command::Play(Action::RotateCcw),
+        Command::Play(Action::Rotate180),
+        Command::Play(Action::Hold),
+        Command::Pause,
+        Command::Restart,
+    ];
+
+    /// Name used in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Play(Action::MoveLeft) => "move_left",
+            Command::Play(Action::MoveRight) => "move_right",
+            Command::Play(Action::SoftDrop) => "soft_drop",
+            Command::Play(Action::HardDrop) => "hard_drop",
+            Command::Play(Action::RotateCw) => "rotate_cw",
+            Command::Play(Action::RotateCcw) => "rotate_ccw",
+            Command::Play(Action::Rotate180) => "rotate_180",
+            Command::Play(Action::Hold) => "hold",
+            Command::Pause => "pause",
+            Command::Restart => "restart",
+        }
+    }
+
+    fn index(self) -> usize {
+        Command::ALL
+            .iter()
+            .position(|&command| command == self)
+            .unwrap()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum KeyPreset {
+    #[default]
+    Arrows,
+    Wasd,
+    Vim,
+}
+
+impl KeyPreset {
+    pub const ALL: [KeyPreset; 3] = [KeyPreset::Arrows, KeyPreset::Wasd, KeyPreset::Vim];
+
+    /// Name used in the config file
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyPreset::Arrows => "arrows",
+            KeyPreset::Wasd => "wasd",
+            KeyPreset::Vim => "vim",
+        }
+    }
+
+    /// Keys for each command in `Command::ALL` order
+    fn keys(self) -> [&'static [KeyCode]; 10] {
+        use KeyCode::*;
+        match self {
+            KeyPreset::Arrows => [
+                &[Left],
+                &[Right],
+                &[Down],
+                &[Char(' ')],
+                &[Up, Char('x')],
+                &[Char('z')],
+                &[Char('a')],
+                &[Char('c')],
+                &[Esc, Char('p')],
+                &[Char('r')],
+            ],
+            KeyPreset::Wasd => [
+                &[Char('a')],
+                &[Char('d')],
+                &[Char('s')],
+                &[Char('w')],
+                &[Char('k')],
+                &[Char('j')],
+                &[Char('l')],
+                &[Char('e')],
+                &[Esc, Char('p')],
+                &[Char('r')],
+            ],
+            KeyPreset::Vim => [
+                &[Char('h')],
+                &[Char('l')],
+                &[Char('j')],
+                &[Char(' ')],
+                &[Char('k')],
+                &[Char('d')],
+                &[Char('f')],
+                &[Char('s')],
+                &[Esc, Char('p')],
+                &[Char('r')],
+            ],
+        }
+    }
+}
+
+/// Keys bound to each command, read from `keys.toml` in the XDG config
+/// directory. The file may name a preset and override single commands:
+///
+/// ```toml
+/// preset = "vim"
+/// hold = ["s", "Tab"]
+/// ```
+#[derive(Clone)]
+pub struct KeyBindings {
+    preset: KeyPreset,
+    keys: Vec<Vec<KeyCode>>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings::preset(KeyPreset::default())
+    }
+}
+
+impl KeyBindings {
+    pub fn preset(preset: KeyPreset) -> KeyBindings {
+        KeyBindings {
+            preset,
+            keys: preset.keys().iter().map(|keys| keys.to_vec()).collect(),
+        }
+    }
+
+    /// Read the config file, a missing file gives the default preset and
+    /// unknown commands or key names are skipped
+    pub fn load() -> KeyBindings {
+        let Some(content) = file_path().and_then(|path| fs::read_to_string(path).ok()) else {
+            return KeyBindings::default();
+        };
+        let entries: Vec<(&str, Vec<&str>)> = content.lines().filter_map(parse_line).collect();
+
+        let preset = entries
+            .iter()
+            .find(|(name, _)| *name == "preset")
+            .and_then(|(_, values)| {
+                KeyPreset::ALL
+                    .into_iter()
+                    .find(|preset| values.first() == Some(&preset.name()))
+            })
+            .unwrap_or_default();
+        let mut bindings = KeyBindings::preset(preset);
+        for (name, values) in entries {
+            if let Some(command) = Command::ALL.into_iter().find(|c| c.name() == name) {
+                bindings.keys[command.index()].clear();
+                for code in values.into_iter().filter_map(parse_key) {
+                    bindings.insert(command, code);
+                }
+            }
+        }
+        // Without a pause key there is no way back to the menus
+        if bindings.keys(Command::Pause).is_empty() {
+            for &code in preset.keys()[Command::Pause.index()] {
+                bindings.insert(Command::Pause, code);
+            }
+        }
+        bindings
+    }
+
+    /// Write the config file, when that fails the changes only last until
+    /// the game exits
+    pub fn save(&self) -> io::Result<()> {
+        let path = file_path().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "no home or config directory")
+        })?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut content = format!("preset = \"{}\"\n", self.preset.name());
+        for command in Command::ALL {
+            let names: Vec<String> = self
+                .keys(command)
+                .iter()
+                .filter_map(|&code| key_name(code))
+                .map(|name| format!("\"{}\"", name))
+                .collect();
+            content.push_str(&format!("{} = [{}]\n", command.name(), names.join(", ")));
+        }
+        fs::write(path, content)
+    }
+
+    pub fn current_preset(&self) -> KeyPreset {
+        self.preset
+    }
+
+    pub fn command(&self, code: KeyCode) -> Option<Command> {
+        Command::ALL
+            .into_iter()
+            .find(|&command| self.keys(command).contains(&code))
+    }
+
+    pub fn keys(&self, command: Command) -> &[KeyCode] {
+        &self.keys[command.index()]
+    }
+
+    /// Add `code` to `command`, a key only ever triggers one command. The
+    /// last pause key is never taken, it is the only way out of a game
+    pub fn bind(&mut self, command: Command, code: KeyCode) {
+        if command != Command::Pause && self.keys(Command::Pause) == [code] {
+            return;
+        }
+        self.insert(command, code);
+    }
+
+    /// Remove the keys of `command`, pause keeps its first key
+    pub fn clear(&mut self, command: Command) {
+        let keys = &mut self.keys[command.index()];
+        let keep = if command == Command::Pause { 1 } else { 0 };
+        keys.truncate(keep);
+    }
+
+    fn insert(&mut self, command: Command, code: KeyCode) {
+        for keys in &mut self.keys {
+            keys.retain(|&key| key != code);
+        }
+        self.keys[command.index()].push(code);
+    }
+}
+
+/// Split a `name = "value"` or `name = ["value", ...]` line, comments,
+/// blank lines and section headers give None
+fn parse_line(line: &str) -> Option<(&str, Vec<&str>)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') || line.starts_with('[') {
+        return None;
+    }
+    let (name, value) = line.split_once('=')?;
+    // Every other piece between quotes is a quoted string
+    let values = value.split('"').skip(1).step_by(2).collect();
+    Some((name.trim(), values))
+}
+
+/// Name of the key in the config file, None for keys that can not be bound
+/// because they have no name
+pub fn key_name(code: KeyCode) -> Option<String> {
+    let name = match code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        // Written between quotes in the config file
+        KeyCode::Char('"') => "Quote".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        KeyCode::Left => "Left".to_string(),
+        KeyCode::Right => "Right".to_string(),
+        KeyCode::Up => "Up".to_string(),
+        KeyCode::Down => "Down".to_string(),
+        KeyCode::Enter => "Enter".to_string(),
+        KeyCode::Esc => "Esc".to_string(),
+        KeyCode::Tab => "Tab".to_string(),
+        KeyCode::Backspace => "Backspace".to_string(),
+        KeyCode::Insert => "Insert".to_string(),
+        KeyCode::Delete => "Delete".to_string(),
+        KeyCode::Home => "Home".to_string(),
+        KeyCode::End => "End".to_string(),
+        KeyCode::PageUp => "PageUp".to_string(),
+        KeyCode::PageDown => "PageDown".to_string(),
+        KeyCode::BackTab => "BackTab".to_string(),
+        KeyCode::CapsLock => "CapsLock".to_string(),
+        KeyCode::ScrollLock => "ScrollLock".to_string(),
+        KeyCode::NumLock => "NumLock".to_string(),
+        KeyCode::PrintScreen => "PrintScreen".to_string(),
+        KeyCode::Pause => "Pause".to_string(),
+        KeyCode::Menu => "Menu".to_string(),
+        KeyCode::KeypadBegin => "KeypadBegin".to_string(),
+        KeyCode::Null | KeyCode::Media(_) | KeyCode::Modifier(_) => return None,
+    };
+    Some(name)
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(KeyCode::Char(c));
+    }
+    let code = match name {
+        "Space" => KeyCode::Char(' '),
+        "Quote" => KeyCode::Char('"'),
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Tab" => KeyCode::Tab,
+        "Backspace" => KeyCode::Backspace,
+        "Insert" => KeyCode::Insert,
+        "Delete" => KeyCode::Delete,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "BackTab" => KeyCode::BackTab,
+        "CapsLock" => KeyCode::CapsLock,
+        "ScrollLock" => KeyCode::ScrollLock,
+        "NumLock" => KeyCode::NumLock,
+        "PrintScreen" => KeyCode::PrintScreen,
+        "Pause" => KeyCode::Pause,
+        "Menu" => KeyCode::Menu,
+        "KeypadBegin" => KeyCode::KeypadBegin,
+        _ => KeyCode::F(name.strip_prefix('F')?.parse().ok()?),
+    };
+    Some(code)
+}
+
+/// `$XDG_CONFIG_HOME/rustris/keys.toml`, falling back to
+/// `~/.config/rustris/keys.toml`
+fn file_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("rustris").join(FILE_NAME))
+}
@@ -1,11 +1,72 @@
-const FIELD_WIDTH: usize = 10;
-const FIELD_HEIGHT: usize = 20;
+//! The game itself: pieces, the playfield, rulesets and the game state.
+
+use std::{collections::VecDeque, mem, ops::RangeInclusive};
+
+use crate::tetris_event::GameEvent;
+use crate::tetris_input::{Action, Direction, Input, InputHandler};
+use crate::tetris_randomizer::{game_rng, GameRng, Randomizer, RandomizerKind};
+use crate::tetris_rotation::{RotationSystem, RotationSystemKind};
+
+pub(crate) const FIELD_WIDTH: usize = 10;
+pub(crate) const FIELD_HEIGHT: usize = 20;
 
 const PIECE_SIDE: usize = 4;
 
+const LINES_PER_LEVEL: u32 = 10;
+// NES points by rows cleared, the NES has no other bonuses
+const NES_LINE_CLEAR_SCORES: [u32; 5] = [0, 40, 100, 300, 1200];
+// Guideline points by rows cleared. These and the guideline bonuses below
+// are multiplied by `GravityCurve::score_multiplier`.
+const LINE_CLEAR_SCORES: [u32; 5] = [0, 100, 300, 500, 800];
+// T-spin points by rows cleared, instead of the line clear points
+const TSPIN_SCORES: [u32; 4] = [400, 800, 1200, 1600];
+const TSPIN_MINI_SCORES: [u32; 3] = [100, 200, 400];
+// Bonus by rows cleared when the clear leaves the playfield empty
+const PERFECT_CLEAR_SCORES: [u32; 5] = [0, 800, 1200, 1800, 2000];
+// Points per combo step
+const COMBO_SCORE: u32 = 50;
+// The fifth SRS quarter turn kick, a T-spin using it is never a mini
+const TST_KICK: usize = 4;
+const SOFT_DROP_SCORE: u32 = 1;
+const HARD_DROP_SCORE: u32 = 2;
+
+/// The game runs at a fixed frame rate, all timing is counted in frames
+pub const FRAMES_PER_SECOND: u32 = 60;
+
+/// Moves and rotations that may reset the lock delay with `LockReset::MoveReset`
+pub const MOVE_RESET_LIMIT: u32 = 15;
+
+/// Largest `SoftDrop::Factor`, the smallest is 1
+pub const MAX_SOFT_DROP_FACTOR: u32 = 40;
+
+/// Fewest upcoming pieces shown
+pub const MIN_PREVIEW: usize = 1;
+/// Most upcoming pieces shown
+pub const MAX_PREVIEW: usize = 6;
+
+// NES gravity in frames per row for levels 0-29, level 29 and above is 1 frame per row
+#[rustfmt::skip]
+const NES_GRAVITY: [u32; 30] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6,
+    5, 5, 5, 4, 4, 4, 3, 3, 3, 2,
+    2, 2, 2, 2, 2, 2, 2, 2, 2, 1,
+];
+
+// Guideline gravity for levels 1-20 as (rows, frames), derived from the
+// seconds per row formula (0.8 - (level - 1) * 0.007) ^ (level - 1)
+#[rustfmt::skip]
+const GUIDELINE_GRAVITY: [(u32, u32); 20] = [
+    (1, 60), (1, 48), (1, 37), (1, 28), (1, 21),
+    (1, 16), (1, 11), (1, 8), (1, 6), (1, 4),
+    (2, 5), (3, 5), (11, 12), (3, 2), (7, 3),
+    (4, 1), (13, 2), (23, 2), (20, 1), (20, 1),
+];
+
 const X: bool = true;
 const O: bool = false;
 
+/// Color of a playfield cell, `Black` is an empty cell
+#[allow(missing_docs)]
 #[derive(Clone, Copy, PartialEq)]
 pub enum BlockColor {
     Black,
@@ -18,66 +79,280 @@ pub enum BlockColor {
     Orange,
 }
 
-#[derive(Clone, Copy)]
-enum PieceRotation {
-    North,
-    East,
-    West,
-    South,
+/// How fast pieces fall at each level
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum GravityCurve {
+    /// NES speeds for levels 0-29
+    #[default]
+    Nes,
+    /// Guideline speeds for levels 1-20
+    Guideline,
 }
 
-type PieceGrid = [[bool; PIECE_SIDE]; PIECE_SIDE];
+impl GravityCurve {
+    /// Gravity at the given level as (rows, frames), the piece falls
+    /// `rows` rows every `frames` frames.
+    pub fn gravity(self, level: u8) -> (u32, u32) {
+        match self {
+            GravityCurve::Nes => {
+                let level = (level as usize).min(NES_GRAVITY.len() - 1);
+                (1, NES_GRAVITY[level])
+            }
+            GravityCurve::Guideline => {
+                let level = (level as usize).clamp(1, GUIDELINE_GRAVITY.len());
+                GUIDELINE_GRAVITY[level - 1]
+            }
+        }
+    }
+    /// Levels a game may start at
+    pub fn start_levels(self) -> RangeInclusive<u8> {
+        match self {
+            GravityCurve::Nes => 0..=29,
+            GravityCurve::Guideline => 1..=15,
+        }
+    }
+    /// What line clear points are multiplied by at the given level, the
+    /// NES counts levels from 0 and the guideline from 1
+    pub fn score_multiplier(self, level: u8) -> u32 {
+        match self {
+            GravityCurve::Nes => level as u32 + 1,
+            GravityCurve::Guideline => (level as u32).max(1),
+        }
+    }
+    fn clamp_start_level(self, level: u8) -> u8 {
+        let levels = self.start_levels();
+        level.clamp(*levels.start(), *levels.end())
+    }
+}
 
-#[derive(Clone, Copy)]
-struct TetrisPieceData {
-    data: PieceGrid,
-    color: BlockColor,
-    width: usize,
-    height: usize,
-    y_start: i8,
+/// When moving or rotating a piece that rests on the stack restarts its lock delay.
+/// Falling to a new lowest row always restarts it.
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum LockReset {
+    /// Every successful move or rotation
+    Infinite,
+    /// At most `MOVE_RESET_LIMIT` times until the piece falls to a new lowest
+    /// row, after that the piece locks as soon as it touches the stack
+    #[default]
+    MoveReset,
+    /// Never, only falling restarts the lock delay
+    StepReset,
 }
 
-impl TetrisPieceData {
-    fn get_data(&self, rotation: PieceRotation) -> TetrisPieceData {
-        let mut rotated_piece = *self;
+impl LockReset {
+    /// Every policy, in menu order
+    pub const ALL: [LockReset; 3] = [
+        LockReset::Infinite,
+        LockReset::MoveReset,
+        LockReset::StepReset,
+    ];
+}
 
-        rotated_piece.width = match rotation {
-            PieceRotation::North => self.width,
-            PieceRotation::West => self.height,
-            PieceRotation::South => self.width,
-            PieceRotation::East => self.height,
-        };
-        rotated_piece.height = match rotation {
-            PieceRotation::North => self.height,
-            PieceRotation::West => self.width,
-            PieceRotation::South => self.height,
-            PieceRotation::East => self.width,
-        };
+/// How soft drop moves the piece
+#[derive(Clone, Copy, PartialEq)]
+pub enum SoftDrop {
+    /// Gravity is multiplied by the factor while soft drop is held, from 1
+    /// to `MAX_SOFT_DROP_FACTOR`
+    Factor(u32),
+    /// The piece moves straight to the floor without locking
+    Sonic,
+}
 
-        rotated_piece.data = [[O; PIECE_SIDE]; PIECE_SIDE];
+impl Default for SoftDrop {
+    fn default() -> Self {
+        SoftDrop::Factor(20)
+    }
+}
 
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let grid_y = match rotation {
-                    PieceRotation::North => y,
-                    PieceRotation::West => x,
-                    PieceRotation::South => self.height - y - 1,
-                    PieceRotation::East => self.width - x - 1,
-                };
-                let grid_x = match rotation {
-                    PieceRotation::North => x,
-                    PieceRotation::West => self.height - y - 1,
-                    PieceRotation::South => self.width - x - 1,
-                    PieceRotation::East => y,
-                };
+/// Convert milliseconds to the nearest number of frames
+pub const fn frames_from_millis(millis: u32) -> u32 {
+    (millis * FRAMES_PER_SECOND + 500) / 1000
+}
+
+/// How a locked piece is scored
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Scoring {
+    /// NES points for line clears only
+    Nes,
+    /// Guideline points with T-spin, combo, back-to-back and perfect clear
+    /// bonuses
+    Guideline,
+}
+
+/// Rule presets, high scores are kept separately for each mode and only for
+/// games played with its unchanged rules
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum GameMode {
+    /// Modern guideline rules
+    #[default]
+    Marathon,
+    /// NES speeds, scoring, randomizer, rotation and shift timing. Pieces
+    /// lock on the first gravity step after landing, there is no ghost and a
+    /// single preview. Hold and 180 rotation are still allowed.
+    Classic,
+}
+
+impl GameMode {
+    /// Every mode, in menu order
+    pub const ALL: [GameMode; 2] = [GameMode::Marathon, GameMode::Classic];
+
+    /// Display name
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Marathon => "Marathon",
+            GameMode::Classic => "Classic",
+        }
+    }
+
+    /// Name in replays, saved games and the high score file
+    pub fn id(self) -> &'static str {
+        match self {
+            GameMode::Marathon => "marathon",
+            GameMode::Classic => "classic",
+        }
+    }
+
+    /// The mode with the name `id`
+    pub fn from_id(id: &str) -> Option<GameMode> {
+        GameMode::ALL.into_iter().find(|mode| mode.id() == id)
+    }
+
+    /// How the mode scores, the same for all of its rulesets so its high
+    /// scores compare
+    pub fn scoring(self) -> Scoring {
+        match self {
+            GameMode::Marathon => Scoring::Guideline,
+            GameMode::Classic => Scoring::Nes,
+        }
+    }
+
+    /// The preset rules of the mode
+    pub fn ruleset(self) -> Ruleset {
+        match self {
+            GameMode::Marathon => Ruleset {
+                mode: self,
+                gravity: GravityCurve::Guideline,
+                randomizer: RandomizerKind::Bag7,
+                rotation: RotationSystemKind::Srs,
+                lock_delay: frames_from_millis(500),
+                lock_reset: LockReset::MoveReset,
+                soft_drop: SoftDrop::default(),
+                ghost: true,
+                das: frames_from_millis(167),
+                arr: frames_from_millis(33),
+                preview: 3,
+            },
+            GameMode::Classic => Ruleset {
+                mode: self,
+                gravity: GravityCurve::Nes,
+                randomizer: RandomizerKind::Nes,
+                rotation: RotationSystemKind::Nes,
+                lock_delay: 0,
+                lock_reset: LockReset::StepReset,
+                soft_drop: SoftDrop::default(),
+                ghost: false,
+                // NES shift timing, 16 frames delay then every 6 frames
+                das: 16,
+                arr: 6,
+                preview: 1,
+            },
+        }
+    }
+}
+
+/// Everything that can be configured about a game
+#[derive(Clone, Copy, PartialEq)]
+pub struct Ruleset {
+    /// The preset this ruleset started from
+    pub mode: GameMode,
+    /// Fall speed by level
+    pub gravity: GravityCurve,
+    /// Source of the piece sequence
+    pub randomizer: RandomizerKind,
+    /// Piece shapes and kicks
+    pub rotation: RotationSystemKind,
+    /// Frames a piece may rest on the stack before it locks, with 0 it locks
+    /// when the next gravity step fails to move it down
+    pub lock_delay: u32,
+    /// Which moves restart the lock delay
+    pub lock_reset: LockReset,
+    /// Soft drop speed
+    pub soft_drop: SoftDrop,
+    /// Show where the current piece would land
+    pub ghost: bool,
+    /// Delayed Auto Shift, frames a direction is held before it repeats
+    pub das: u32,
+    /// Auto Repeat Rate, frames between repeated shifts, 0 shifts to the wall at once
+    pub arr: u32,
+    /// Number of upcoming pieces shown, clamped to MIN_PREVIEW..=MAX_PREVIEW
+    pub preview: usize,
+}
 
-                rotated_piece.data[grid_y][grid_x] = self.data[y][x];
+impl Default for Ruleset {
+    fn default() -> Self {
+        GameMode::default().ruleset()
+    }
+}
+
+impl Ruleset {
+    /// Whether these are the unchanged rules of `mode`. The ghost only shows
+    /// where the piece lands and may be turned off.
+    pub fn is_preset(&self) -> bool {
+        *self
+            == Ruleset {
+                ghost: self.ghost,
+                ..self.mode.ruleset()
             }
+    }
+}
+
+/// Rotation state of a piece, in clockwise order starting from the spawn
+/// state
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PieceRotation {
+    /// Spawn state
+    North,
+    /// One clockwise turn from spawn
+    East,
+    /// Half a turn from spawn
+    South,
+    /// One counterclockwise turn from spawn
+    West,
+}
+
+impl PieceRotation {
+    /// The state one clockwise turn away
+    pub fn cw(self) -> PieceRotation {
+        match self {
+            PieceRotation::North => PieceRotation::East,
+            PieceRotation::East => PieceRotation::South,
+            PieceRotation::South => PieceRotation::West,
+            PieceRotation::West => PieceRotation::North,
+        }
+    }
+    /// The state one counterclockwise turn away
+    pub fn ccw(self) -> PieceRotation {
+        match self {
+            PieceRotation::North => PieceRotation::West,
+            PieceRotation::West => PieceRotation::South,
+            PieceRotation::South => PieceRotation::East,
+            PieceRotation::East => PieceRotation::North,
         }
-        rotated_piece
     }
 }
 
+/// Blocks of a piece inside its rotation box as `grid[y][x]`, y points down
+pub type PieceGrid = [[bool; PIECE_SIDE]; PIECE_SIDE];
+
+// Pieces are stored in their guideline spawn orientation, inside a rotation
+// box of `size` x `size` blocks
+#[derive(Clone, Copy)]
+struct TetrisPieceData {
+    data: PieceGrid,
+    color: BlockColor,
+    size: usize,
+}
+
 #[rustfmt::skip]
 const IPIECE: TetrisPieceData = TetrisPieceData {
     data: [
@@ -87,9 +362,7 @@ const IPIECE: TetrisPieceData = TetrisPieceData {
     [O, O, O, O]
     ],
     color: BlockColor::Red,
-    width: 4,
-    height: 3,
-    y_start: -1,
+    size: 4,
 };
 #[rustfmt::skip]
 const LPIECE: TetrisPieceData = TetrisPieceData {
@@ -100,9 +373,7 @@ const LPIECE: TetrisPieceData = TetrisPieceData {
     [O, O, O, O]
     ],
     color: BlockColor::Yellow,
-    width: 3,
-    height: 3,
-    y_start: 0,
+    size: 3,
 };
 #[rustfmt::skip]
 const JPIECE: TetrisPieceData = TetrisPieceData {
@@ -113,9 +384,7 @@ const JPIECE: TetrisPieceData = TetrisPieceData {
     [O, O, O, O]
     ],
     color: BlockColor::Orange,
-    width: 3,
-    height: 3,
-    y_start: 0,
+    size: 3,
 };
 #[rustfmt::skip]
 const OPIECE: TetrisPieceData = TetrisPieceData {
@@ -126,9 +395,7 @@ const OPIECE: TetrisPieceData = TetrisPieceData {
     [O, O, O, O]
     ],
     color: BlockColor::Cyan,
-    width: 2,
-    height: 2,
-    y_start: 0,
+    size: 2,
 };
 #[rustfmt::skip]
 const SPIECE: TetrisPieceData = TetrisPieceData {
@@ -139,9 +406,7 @@ const SPIECE: TetrisPieceData = TetrisPieceData {
     [O, O, O, O]
     ],
     color: BlockColor::Green,
-    width: 3,
-    height: 2,
-    y_start: 0,
+    size: 3,
 };
 #[rustfmt::skip]
 const ZPIECE: TetrisPieceData = TetrisPieceData {
@@ -152,9 +417,7 @@ const ZPIECE: TetrisPieceData = TetrisPieceData {
     [O, O, O, O]
     ],
     color: BlockColor::Magenta,
-    width: 3,
-    height: 2,
-    y_start: 0,
+    size: 3,
 };
 #[rustfmt::skip]
 const TPIECE: TetrisPieceData = TetrisPieceData {
@@ -165,49 +428,161 @@ const TPIECE: TetrisPieceData = TetrisPieceData {
     [O, O, O, O]
     ],
     color: BlockColor::Blue,
-    width: 3,
-    height: 3,
-    y_start: 0,
+    size: 3,
 };
 
 const TETRISPIECES: [TetrisPieceData; 7] = [IPIECE, LPIECE, JPIECE, OPIECE, SPIECE, ZPIECE, TPIECE];
 
-#[derive(Clone, Copy)]
-struct CurrentPiece<'a> {
-    piece: &'a TetrisPieceData,
-    x: i8,
-    y: i8,
-    rotation: PieceRotation,
+/// A T piece locked after a rotation with three of the four corners around
+/// its center occupied, a full T-spin needs both corners its point faces
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TSpin {
+    /// Only one of the corners the point faces is occupied
+    Mini,
+    /// Both corners the point faces are occupied, or the T was kicked in
+    /// with the fifth SRS kick
+    Full,
+}
+
+/// Rows cleared by a locked piece, `rows` is 0 for a T-spin without lines
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LineClear {
+    /// Number of rows cleared
+    pub rows: u32,
+    /// Kind of T-spin, if any
+    pub tspin: Option<TSpin>,
+    /// Consecutive clears before this one, 0 for the first
+    pub combo: u32,
+    /// A tetris or T-spin clear right after another one, scores 1.5 times
+    pub back_to_back: bool,
+    /// The clear left the playfield empty
+    pub perfect_clear: bool,
+    /// Frame the piece locked on, see `TetrisState::frames`
+    pub frame: u64,
+}
+
+/// The last successful move of the current piece, T-spins need a rotation
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum LastMove {
+    Spawn,
+    /// Sideways or down
+    Shift,
+    /// Rotation using the kick at `kick` in the rotation system's kick list
+    Rotation {
+        kick: usize,
+        half_turn: bool,
+    },
+}
+
+/// The seven tetrominoes
+#[allow(missing_docs)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PieceType {
+    I,
+    L,
+    J,
+    O,
+    S,
+    Z,
+    T,
+}
+
+impl PieceType {
+    /// Every piece
+    pub const ALL: [PieceType; 7] = [
+        PieceType::I,
+        PieceType::L,
+        PieceType::J,
+        PieceType::O,
+        PieceType::S,
+        PieceType::Z,
+        PieceType::T,
+    ];
+
+    fn data(self) -> &'static TetrisPieceData {
+        &TETRISPIECES[self as usize]
+    }
+    /// Color of the blocks of the piece
+    pub fn color(self) -> BlockColor {
+        self.data().color
+    }
+    /// The piece in its guideline spawn orientation
+    pub fn shape(self) -> PieceGrid {
+        self.data().data
+    }
+    /// Side of the box the piece rotates in
+    pub fn box_size(self) -> usize {
+        self.data().size
+    }
+}
+
+/// A piece in play, its rotation box has the top left corner at (x, y) on
+/// the playfield and may stick out above the top
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CurrentPiece {
+    pub(crate) piece: PieceType,
+    pub(crate) x: i8,
+    pub(crate) y: i8,
+    pub(crate) rotation: PieceRotation,
+    pub(crate) shape: PieceGrid,
+}
+
+impl CurrentPiece {
+    /// Which piece it is
+    pub fn piece(&self) -> PieceType {
+        self.piece
+    }
+    /// Column of the left side of the rotation box
+    pub fn x(&self) -> i8 {
+        self.x
+    }
+    /// Row of the top of the rotation box, negative above the playfield
+    pub fn y(&self) -> i8 {
+        self.y
+    }
+    /// Current rotation state
+    pub fn rotation(&self) -> PieceRotation {
+        self.rotation
+    }
+    /// Blocks inside the rotation box in the current rotation state
+    pub fn shape(&self) -> PieceGrid {
+        self.shape
+    }
+    /// Playfield coordinates of the blocks of the piece
+    pub fn blocks(&self) -> impl Iterator<Item = (i8, i8)> + '_ {
+        self.shape.iter().enumerate().flat_map(move |(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, &block)| block)
+                .map(move |(x, _)| (x as i8 + self.x, y as i8 + self.y))
+        })
+    }
 }
 
-impl<'a> Default for CurrentPiece<'a> {
+impl Default for CurrentPiece {
     fn default() -> Self {
         CurrentPiece {
-            piece: &IPIECE,
+            piece: PieceType::I,
             x: 0,
             y: 0,
             rotation: PieceRotation::North,
+            shape: PieceType::I.shape(),
         }
     }
 }
 
+/// The grid of locked blocks, row 0 is the top
 #[derive(Clone, Copy)]
 pub struct Playfield {
-    data: [[BlockColor; FIELD_WIDTH]; FIELD_HEIGHT],
+    pub(crate) data: [[BlockColor; FIELD_WIDTH]; FIELD_HEIGHT],
 }
 
 impl Playfield {
-    fn draw(&mut self, current: &CurrentPiece) {
-        let rotated_piece = current.piece.get_data(current.rotation);
-
-        for y in 0..rotated_piece.height {
-            for x in 0..rotated_piece.width {
-                if rotated_piece.data[y][x] {
-                    let grid_x = x as i8 + current.x;
-                    let grid_y = y as i8 + current.y;
-                    self.data[grid_y as usize][grid_x as usize] = current.piece.color;
-                }
-            }
+    /// Color the blocks of `current` into the playfield without clearing rows
+    pub fn draw(&mut self, current: &CurrentPiece) {
+        // Blocks above the top of the playfield are not drawn
+        for (x, y) in current.blocks().filter(|&(_, y)| y >= 0) {
+            self.data[y as usize][x as usize] = current.piece.color();
         }
     }
     fn test_row(&self, row: usize) -> bool {
@@ -221,61 +596,53 @@ impl Playfield {
         }
         self.data[0] = [BlockColor::Black; FIELD_WIDTH];
     }
-    fn test_and_remove_rows(&mut self) {
-        let mut row = Playfield::height() - 1;
-        loop {
-            if self.test_row(row) {
-                self.remove_row(row);
-            } else {
-                if row == 0 {
-                    break;
-                }
-                row -= 1;
-            }
+    /// Remove the full rows and return their indices
+    fn test_and_remove_rows(&mut self) -> Vec<usize> {
+        let rows: Vec<usize> = (0..Playfield::height())
+            .filter(|&row| self.test_row(row))
+            .collect();
+        // Removing from the top down keeps the rows below in place
+        for &row in &rows {
+            self.remove_row(row);
         }
+        rows
     }
-    fn place(&mut self, piece: CurrentPiece) {
-        let rotated_piece = piece.piece.get_data(piece.rotation);
-        for y in 0..rotated_piece.height {
-            for x in 0..rotated_piece.width {
-                if rotated_piece.data[y][x] {
-                    let grid_x = x as i8 + piece.x;
-                    let grid_y = y as i8 + piece.y;
-                    self.data[grid_y as usize][grid_x as usize] = rotated_piece.color;
-                }
-            }
-        }
+    /// Whether no cell holds a block
+    pub fn is_empty(&self) -> bool {
+        self.data
+            .iter()
+            .flatten()
+            .all(|&color| color == BlockColor::Black)
+    }
+    fn place(&mut self, piece: CurrentPiece) -> Vec<usize> {
+        self.draw(&piece);
         self.test_and_remove_rows()
     }
-    fn try_piece(&self, piece: CurrentPiece) -> bool {
-        let rotated_piece = piece.piece.get_data(piece.rotation);
-
-        for y in 0..rotated_piece.height {
-            for x in 0..rotated_piece.width {
-                if rotated_piece.data[y][x] {
-                    let grid_x = x as i8 + piece.x;
-                    let grid_y = y as i8 + piece.y;
-                    if grid_x < 0 || grid_x >= Playfield::width() as i8 {
-                        return false;
-                    }
-                    if grid_y < 0 || grid_y >= Playfield::height() as i8 {
-                        return false;
-                    }
-                    if self.data[grid_y as usize][grid_x as usize] != BlockColor::Black {
-                        return false;
-                    }
-                }
-            }
+    /// Whether a block may be at (x, y), outside the walls and floor is
+    /// never free but above the top always is
+    pub fn is_free(&self, x: i8, y: i8) -> bool {
+        if x < 0 || x >= Playfield::width() as i8 {
+            return false;
         }
-
-        true
+        if y >= Playfield::height() as i8 {
+            return false;
+        }
+        // Pieces may be kicked above the top of the playfield
+        y < 0 || self.data[y as usize][x as usize] == BlockColor::Black
     }
+    /// Whether every block of `piece` is free
+    pub fn try_piece(&self, piece: CurrentPiece) -> bool {
+        piece.blocks().all(|(x, y)| self.is_free(x, y))
+    }
+    /// Number of columns
     pub const fn width() -> usize {
         FIELD_WIDTH
     }
+    /// Number of rows
     pub const fn height() -> usize {
         FIELD_HEIGHT
     }
+    /// The cells as `data[y][x]`
     pub fn data(&self) -> &[[BlockColor; FIELD_WIDTH]; FIELD_HEIGHT] {
         &self.data
     }
@@ -289,73 +656,273 @@ impl Default for Playfield {
     }
 }
 
-#[derive(Default)]
+/// A game of Tetris, advanced one frame at a time with `tick`
 pub struct TetrisState {
-    level: u8,
-    field: Playfield,
-    current: CurrentPiece<'static>,
+    pub(crate) level: u8,
+    pub(crate) start_level: u8,
+    pub(crate) lines: u32,
+    pub(crate) score: u32,
+    pub(crate) field: Playfield,
+    pub(crate) current: CurrentPiece,
     game_over: bool,
+    pub(crate) ruleset: Ruleset,
+    pub(crate) gravity_counter: u32,
+    pub(crate) queue: VecDeque<PieceType>,
+    pub(crate) held: Option<PieceType>,
+    pub(crate) hold_used: bool,
+    pub(crate) randomizer: Box<dyn Randomizer>,
+    pub(crate) rng: GameRng,
+    pub(crate) seed: u64,
+    pub(crate) rotation_system: Box<dyn RotationSystem>,
+    pub(crate) lock_timer: u32,
+    pub(crate) move_resets: u32,
+    pub(crate) lowest_y: i8,
+    pub(crate) last_move: LastMove,
+    pub(crate) last_clear: Option<LineClear>,
+    pub(crate) clear_streak: u32,
+    pub(crate) difficult_streak: u32,
+    pub(crate) perfect_clears: u32,
+    soft_drop_held: bool,
+    pub(crate) soft_drop_was_held: bool,
+    input: InputHandler,
+    pub(crate) frames: u64,
+    pub(crate) pieces: u32,
+    pub(crate) events: Vec<GameEvent>,
 }
 
 impl TetrisState {
+    /// A game with the default ruleset and a random seed
     pub fn new(level: u8) -> TetrisState {
-        let mut state = TetrisState::default();
-        state.restart(level);
+        TetrisState::with_ruleset(level, Ruleset::default())
+    }
+    /// A game with a random seed
+    pub fn with_ruleset(level: u8, ruleset: Ruleset) -> TetrisState {
+        TetrisState::with_seed(level, ruleset, rand::random())
+    }
+    /// A game where all randomness is derived from `seed`, the same seed and
+    /// the same inputs always give the same game.
+    pub fn with_seed(level: u8, ruleset: Ruleset, seed: u64) -> TetrisState {
+        let mut state = TetrisState {
+            level,
+            start_level: level,
+            lines: 0,
+            score: 0,
+            field: Playfield::default(),
+            current: CurrentPiece::default(),
+            game_over: false,
+            ruleset,
+            gravity_counter: 0,
+            queue: VecDeque::new(),
+            held: None,
+            hold_used: false,
+            randomizer: ruleset.randomizer.create(),
+            rng: game_rng(seed),
+            seed,
+            rotation_system: ruleset.rotation.create(),
+            lock_timer: 0,
+            move_resets: 0,
+            lowest_y: 0,
+            last_move: LastMove::Spawn,
+            last_clear: None,
+            clear_streak: 0,
+            difficult_streak: 0,
+            perfect_clears: 0,
+            soft_drop_held: false,
+            soft_drop_was_held: false,
+            input: InputHandler::default(),
+            frames: 0,
+            pieces: 0,
+            events: Vec::new(),
+        };
+        state.restart_with_seed(level, seed);
         state
     }
+    /// Start a new game with a fresh random seed
     pub fn restart(&mut self, level: u8) {
+        self.restart_with_seed(level, rand::random());
+    }
+    /// Start a new game, `level` is clamped to the start levels of the gravity curve
+    pub fn restart_with_seed(&mut self, level: u8, seed: u64) {
+        let level = self.ruleset.gravity.clamp_start_level(level);
+        self.rng = game_rng(seed);
+        self.seed = seed;
         self.game_over = false;
         self.level = level;
+        self.start_level = level;
+        self.lines = 0;
+        self.score = 0;
+        self.frames = 0;
+        self.pieces = 0;
+        self.events.clear();
+        self.last_clear = None;
+        self.clear_streak = 0;
+        self.difficult_streak = 0;
+        self.perfect_clears = 0;
         self.field = Playfield::default();
+        self.ruleset.preview = self.ruleset.preview.clamp(MIN_PREVIEW, MAX_PREVIEW);
+        self.randomizer = self.ruleset.randomizer.create();
+        self.rotation_system = self.ruleset.rotation.create();
+        self.input = InputHandler::default();
+        self.soft_drop_held = false;
+        self.soft_drop_was_held = false;
+        self.queue.clear();
+        self.held = None;
+        self.hold_used = false;
+        for _ in 0..self.ruleset.preview {
+            let piece = self.randomizer.next_piece(&mut self.rng);
+            self.queue.push_back(piece);
+        }
         self.new_piece();
     }
     fn new_piece(&mut self) {
-        let rand_val: usize = (rand::random::<u8>() as usize) % TETRISPIECES.len();
-        let piece = &TETRISPIECES[rand_val];
+        self.queue
+            .push_back(self.randomizer.next_piece(&mut self.rng));
+        let piece = self.queue.pop_front().unwrap();
+        self.spawn(piece);
+    }
+    fn spawn(&mut self, piece: PieceType) {
+        let shape = self.rotation_system.shape(piece, PieceRotation::North);
+        // Spawn with the top row of blocks on the top row of the playfield
+        let top = shape
+            .iter()
+            .position(|row| row.contains(&true))
+            .unwrap_or(0);
         self.current = CurrentPiece {
             piece,
-            x: ((FIELD_WIDTH - piece.width) / 2) as i8,
-            y: piece.y_start,
+            x: ((FIELD_WIDTH - piece.box_size()) / 2) as i8,
+            y: -(top as i8),
             rotation: PieceRotation::North,
+            shape,
         };
+        self.gravity_counter = 0;
+        self.lock_timer = 0;
+        self.move_resets = 0;
+        self.lowest_y = self.current.y;
+        self.last_move = LastMove::Spawn;
         self.game_over = !self.field.try_piece(self.current);
+        self.events.push(if self.game_over {
+            GameEvent::TopOut
+        } else {
+            GameEvent::Spawned(self.current)
+        });
     }
-    pub fn rotate_ccw(&mut self) {
-        let mut piece = self.current;
-        piece.rotation = match self.current.rotation {
-            PieceRotation::North => PieceRotation::West,
-            PieceRotation::West => PieceRotation::South,
-            PieceRotation::South => PieceRotation::East,
-            PieceRotation::East => PieceRotation::North,
+    fn rotate(&mut self, rotation: PieceRotation) {
+        if self.game_over {
+            return;
+        }
+        let from = self.current.rotation;
+        let piece = CurrentPiece {
+            rotation,
+            shape: self.rotation_system.shape(self.current.piece, rotation),
+            ..self.current
         };
-        if self.field.try_piece(piece) {
-            self.current = piece;
+        let kicks = self.rotation_system.kicks(piece.piece, from, rotation);
+        for (index, &(dx, dy)) in kicks.iter().enumerate() {
+            let kicked = CurrentPiece {
+                x: piece.x + dx,
+                y: piece.y - dy,
+                ..piece
+            };
+            if self.field.try_piece(kicked) {
+                self.current = kicked;
+                self.events.push(GameEvent::Rotated {
+                    from,
+                    to: rotation,
+                    kick: index,
+                    offset: (dx, dy),
+                });
+                self.last_move = LastMove::Rotation {
+                    kick: index,
+                    half_turn: rotation == from.cw().cw(),
+                };
+                self.piece_moved();
+                return;
+            }
+            if index == 0 {
+                let blocked = piece.blocks().find(|&(x, y)| !self.field.is_free(x, y));
+                if let Some((x, y)) = blocked {
+                    let blocked = ((x - piece.x) as usize, (y - piece.y) as usize);
+                    if !self.rotation_system.allow_kick(piece.piece, from, blocked) {
+                        return;
+                    }
+                }
+            }
         }
     }
+    /// Rotate the current piece counterclockwise, kicking it if needed
+    pub fn rotate_ccw(&mut self) {
+        self.rotate(self.current.rotation.ccw());
+    }
+    /// Rotate the current piece clockwise, kicking it if needed
     pub fn rotate_cw(&mut self) {
-        let mut piece = self.current;
-        piece.rotation = match self.current.rotation {
-            PieceRotation::North => PieceRotation::East,
-            PieceRotation::East => PieceRotation::South,
-            PieceRotation::South => PieceRotation::West,
-            PieceRotation::West => PieceRotation::North,
-        };
-        if self.field.try_piece(piece) {
-            self.current = piece;
-        }
+        self.rotate(self.current.rotation.cw());
     }
+    /// Rotate the current piece half a turn, kicking it if needed
+    pub fn rotate_180(&mut self) {
+        self.rotate(self.current.rotation.cw().cw());
+    }
+    /// Shift the current piece one column left if there is room
     pub fn move_left(&mut self) {
+        if self.game_over {
+            return;
+        }
         let mut piece = self.current;
         piece.x -= 1;
         if self.field.try_piece(piece) {
             self.current = piece;
+            self.events.push(GameEvent::Moved { dx: -1, dy: 0 });
+            self.last_move = LastMove::Shift;
+            self.piece_moved();
         }
     }
+    /// Shift the current piece one column right if there is room
     pub fn move_right(&mut self) {
+        if self.game_over {
+            return;
+        }
         let mut piece = self.current;
         piece.x += 1;
         if self.field.try_piece(piece) {
             self.current = piece;
+            self.events.push(GameEvent::Moved { dx: 1, dy: 0 });
+            self.last_move = LastMove::Shift;
+            self.piece_moved();
+        }
+    }
+    // Apply the lock reset policy after a successful move or rotation
+    fn piece_moved(&mut self) {
+        match self.ruleset.lock_reset {
+            LockReset::Infinite => self.lock_timer = 0,
+            LockReset::MoveReset => {
+                if self.move_resets < MOVE_RESET_LIMIT {
+                    self.move_resets += 1;
+                    self.lock_timer = 0;
+                }
+            }
+            LockReset::StepReset => {}
+        }
+    }
+    fn on_ground(&self) -> bool {
+        let mut piece = self.current;
+        piece.y += 1;
+        !self.field.try_piece(piece)
+    }
+    /// Swap the current piece with the held one, or with the next piece from
+    /// the queue when nothing is held. Allowed once per piece until it locks.
+    pub fn hold(&mut self) {
+        if self.hold_used || self.game_over {
+            return;
+        }
+        self.events.push(GameEvent::Held(self.current.piece));
+        match self.held.replace(self.current.piece) {
+            Some(piece) => self.spawn(piece),
+            None => self.new_piece(),
+        }
+        self.hold_used = true;
+    }
+    fn moved_down(&mut self, rows: i8) {
+        if rows > 0 {
+            self.events.push(GameEvent::Moved { dx: 0, dy: rows });
         }
     }
     fn drop_one_line(&mut self) -> bool {
@@ -363,30 +930,599 @@ impl TetrisState {
         piece.y += 1;
         if self.field.try_piece(piece) {
             self.current = piece;
+            self.last_move = LastMove::Shift;
+            if piece.y > self.lowest_y {
+                self.lowest_y = piece.y;
+                self.lock_timer = 0;
+                self.move_resets = 0;
+            }
             return true;
         }
         false
     }
-    pub fn tick(&mut self) {
-        if !self.drop_one_line() {
-            self.field.place(self.current);
-            self.new_piece();
+    /// Three corner T-spin check for the current piece, the center and the
+    /// direction the T points are found from its blocks so every rotation
+    /// system works
+    fn tspin(&self) -> Option<TSpin> {
+        let LastMove::Rotation { kick, half_turn } = self.last_move else {
+            return None;
+        };
+        if self.current.piece != PieceType::T {
+            return None;
+        }
+        let blocks: Vec<(i8, i8)> = self.current.blocks().collect();
+        let neighbors = |&(x, y): &(i8, i8)| {
+            blocks
+                .iter()
+                .filter(move |&&(bx, by)| (bx - x).abs() + (by - y).abs() == 1)
+        };
+        let center = *blocks.iter().find(|block| neighbors(block).count() == 3)?;
+        // The two side blocks cancel out and leave the point
+        let (px, py) = neighbors(&center).fold((0, 0), |(px, py), &(x, y)| {
+            (px + x - center.0, py + y - center.1)
+        });
+
+        let corners = [(-1, -1), (1, -1), (-1, 1), (1, 1)];
+        let occupied = |&&(dx, dy): &&(i8, i8)| !self.field.is_free(center.0 + dx, center.1 + dy);
+        if corners.iter().filter(occupied).count() < 3 {
+            return None;
+        }
+        let front = corners
+            .iter()
+            .filter(|&&(dx, dy)| dx * px + dy * py == 1)
+            .filter(occupied)
+            .count();
+        if front == 2 || (kick == TST_KICK && !half_turn) {
+            Some(TSpin::Full)
+        } else {
+            Some(TSpin::Mini)
         }
     }
-    pub fn drop(&mut self) {
-        while self.drop_one_line() {}
-        self.field.place(self.current);
+    fn lock_piece(&mut self) {
+        // Lock out, blocks locked above the playfield have nowhere to go
+        let locked_out = self.current.blocks().any(|(_, y)| y < 0);
+        let tspin = self.tspin();
+        let cleared_rows = self.field.place(self.current);
+        let rows = cleared_rows.len() as u32;
+        self.pieces += 1;
+        self.events.push(GameEvent::Locked(self.current));
+        if let Some(tspin) = tspin {
+            self.events.push(GameEvent::TSpin(tspin));
+        }
+        // Locking without a clear breaks the combo but not back-to-back
+        let mut back_to_back = false;
+        if rows > 0 {
+            self.clear_streak += 1;
+            let difficult = rows == 4 || tspin.is_some();
+            back_to_back = difficult && self.difficult_streak > 0;
+            self.difficult_streak = if difficult {
+                self.difficult_streak + 1
+            } else {
+                0
+            };
+        } else {
+            self.clear_streak = 0;
+        }
+        let perfect_clear = rows > 0 && self.field.is_empty();
+        if perfect_clear {
+            self.perfect_clears += 1;
+        }
+        let points = match self.ruleset.mode.scoring() {
+            Scoring::Nes => NES_LINE_CLEAR_SCORES[rows as usize],
+            Scoring::Guideline => {
+                let mut points = match tspin {
+                    Some(TSpin::Full) => TSPIN_SCORES[rows as usize],
+                    Some(TSpin::Mini) => {
+                        TSPIN_MINI_SCORES[(rows as usize).min(TSPIN_MINI_SCORES.len() - 1)]
+                    }
+                    None => LINE_CLEAR_SCORES[rows as usize],
+                };
+                if back_to_back {
+                    points = points * 3 / 2;
+                }
+                if rows > 0 {
+                    points += COMBO_SCORE * self.combo();
+                }
+                if perfect_clear {
+                    points += PERFECT_CLEAR_SCORES[rows as usize];
+                }
+                points
+            }
+        };
+        self.score += points * self.ruleset.gravity.score_multiplier(self.level);
+        if rows > 0 || tspin.is_some() {
+            let clear = LineClear {
+                rows,
+                tspin,
+                combo: self.combo(),
+                back_to_back,
+                perfect_clear,
+                frame: self.frames,
+            };
+            self.last_clear = Some(clear);
+            if rows > 0 {
+                self.events.push(GameEvent::LinesCleared {
+                    rows: cleared_rows,
+                    clear,
+                });
+            }
+        }
+        if rows > 0 && self.combo() > 0 {
+            self.events.push(GameEvent::Combo(self.combo()));
+        }
+        self.lines += rows;
+        let level = self.start_level as u32 + self.lines / LINES_PER_LEVEL;
+        let level = level.min(u8::MAX as u32) as u8;
+        if level > self.level {
+            self.events.push(GameEvent::LevelUp(level));
+        }
+        self.level = level;
+        self.hold_used = false;
+        if locked_out {
+            self.game_over = true;
+            self.events.push(GameEvent::TopOut);
+            return;
+        }
         self.new_piece();
     }
+    /// An action key went down, movement and soft drop keep repeating
+    /// every frame until the matching `release`. Like the other actions it
+    /// does nothing once the game is over.
+    pub fn press(&mut self, action: Action) {
+        if self.game_over {
+            return;
+        }
+        self.input.press(action);
+        match action {
+            Action::MoveLeft => self.move_left(),
+            Action::MoveRight => self.move_right(),
+            Action::SoftDrop => self.soft_drop(),
+            Action::HardDrop => self.hard_drop(),
+            Action::RotateCw => self.rotate_cw(),
+            Action::RotateCcw => self.rotate_ccw(),
+            Action::Rotate180 => self.rotate_180(),
+            Action::Hold => self.hold(),
+        }
+    }
+    /// An action key went up
+    pub fn release(&mut self, action: Action) {
+        self.input.release(action);
+    }
+    /// Forget all held keys, for when key releases can not be seen
+    pub fn release_all(&mut self) {
+        self.input = InputHandler::default();
+    }
+    fn handle_held_input(&mut self) {
+        if self.input.soft_drop_held() {
+            self.soft_drop();
+        }
+        if let Some((direction, cells)) = self.input.auto_shift(self.ruleset.das, self.ruleset.arr)
+        {
+            for _ in 0..cells {
+                let x = self.current.x;
+                match direction {
+                    Direction::Left => self.move_left(),
+                    Direction::Right => self.move_right(),
+                }
+                if self.current.x == x {
+                    break;
+                }
+            }
+        }
+    }
+    /// Play one frame without a terminal or a clock: apply the key presses
+    /// and releases of the frame in order, then `tick`. Returns the events
+    /// since they were last taken.
+    pub fn step(&mut self, inputs: &[Input]) -> Vec<GameEvent> {
+        for &input in inputs {
+            if self.game_over {
+                break;
+            }
+            match input {
+                Input::Press(action) => self.press(action),
+                Input::Release(action) => self.release(action),
+            }
+        }
+        self.tick();
+        self.take_events()
+    }
+    /// Events since they were last taken, oldest first. They are kept until
+    /// taken, so a front-end calling `tick` itself should take them every
+    /// frame.
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        mem::take(&mut self.events)
+    }
+    /// Advance the game by one frame, see `FRAMES_PER_SECOND`.
+    pub fn tick(&mut self) {
+        if self.game_over {
+            return;
+        }
+        self.frames += 1;
+        self.handle_held_input();
+        let soft_drop = self.soft_drop_held;
+        self.soft_drop_was_held = soft_drop;
+        self.soft_drop_held = false;
+
+        let (mut rows, frames) = self.ruleset.gravity.gravity(self.level);
+        if let (true, SoftDrop::Factor(factor)) = (soft_drop, self.ruleset.soft_drop) {
+            rows *= factor;
+        }
+        self.gravity_counter += rows;
+        let mut fallen = 0;
+        let mut blocked = false;
+        while self.gravity_counter >= frames {
+            self.gravity_counter -= frames;
+            if !self.drop_one_line() {
+                self.gravity_counter = 0;
+                blocked = true;
+                break;
+            }
+            fallen += 1;
+            if soft_drop {
+                self.score += SOFT_DROP_SCORE;
+            }
+        }
+        self.moved_down(fallen);
+
+        if self.ruleset.lock_delay == 0 {
+            // Like the NES, the piece rests on the stack until gravity fails
+            // to move it down
+            if blocked {
+                self.lock_piece();
+            }
+        } else if self.on_ground() {
+            self.lock_timer += 1;
+            let out_of_resets = self.ruleset.lock_reset == LockReset::MoveReset
+                && self.move_resets >= MOVE_RESET_LIMIT;
+            if self.lock_timer >= self.ruleset.lock_delay || out_of_resets {
+                self.lock_piece();
+            }
+        }
+    }
+    /// Soft drop for the current frame, call it every frame the soft drop is held.
+    /// The piece moves one row down right away when the soft drop starts.
+    pub fn soft_drop(&mut self) {
+        if self.game_over {
+            return;
+        }
+        match self.ruleset.soft_drop {
+            SoftDrop::Factor(_) => {
+                if !self.soft_drop_was_held && !self.soft_drop_held && self.drop_one_line() {
+                    self.score += SOFT_DROP_SCORE;
+                    self.moved_down(1);
+                }
+            }
+            SoftDrop::Sonic => {
+                let mut fallen = 0;
+                while self.drop_one_line() {
+                    self.score += SOFT_DROP_SCORE;
+                    fallen += 1;
+                }
+                self.moved_down(fallen);
+            }
+        }
+        self.soft_drop_held = true;
+    }
+    /// Hard drop, the current piece falls to the bottom and locks
+    pub fn hard_drop(&mut self) {
+        if self.game_over {
+            return;
+        }
+        let mut cells = 0;
+        while self.drop_one_line() {
+            cells += 1;
+        }
+        self.moved_down(cells as i8);
+        self.score += cells * HARD_DROP_SCORE;
+        self.lock_piece();
+    }
+    /// Blocks of the current piece at the position a hard drop would land it
+    pub fn ghost_blocks(&self) -> Vec<(usize, usize)> {
+        let mut ghost = self.current;
+        loop {
+            let mut piece = ghost;
+            piece.y += 1;
+            if !self.field.try_piece(piece) {
+                break;
+            }
+            ghost = piece;
+        }
+        ghost
+            .blocks()
+            .filter(|&(_, y)| y >= 0)
+            .map(|(x, y)| (x as usize, y as usize))
+            .collect()
+    }
+    /// The piece in play
+    pub fn current_piece(&self) -> CurrentPiece {
+        self.current
+    }
+    /// The playfield with the current piece drawn in
     pub fn field(&self) -> Playfield {
         let mut field = self.field;
         field.draw(&self.current);
         field
     }
+    /// Current level
     pub fn level(&self) -> u8 {
         self.level
     }
+    /// Frames played, see `FRAMES_PER_SECOND`
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+    /// Consecutive pieces that cleared lines, not counting the first
+    pub fn combo(&self) -> u32 {
+        self.clear_streak.saturating_sub(1)
+    }
+    /// Tetrises and T-spin clears in a row, not counting the first
+    pub fn back_to_back(&self) -> u32 {
+        self.difficult_streak.saturating_sub(1)
+    }
+    /// The last line clear or T-spin
+    pub fn last_clear(&self) -> Option<LineClear> {
+        self.last_clear
+    }
+    /// Clears that left the playfield empty
+    pub fn perfect_clears(&self) -> u32 {
+        self.perfect_clears
+    }
+    /// Pieces locked so far
+    pub fn pieces_placed(&self) -> u32 {
+        self.pieces
+    }
+    /// Pieces locked per second of game time
+    pub fn pieces_per_second(&self) -> f64 {
+        if self.frames == 0 {
+            return 0.0;
+        }
+        self.pieces as f64 * FRAMES_PER_SECOND as f64 / self.frames as f64
+    }
+    /// Seed all randomness of the game is derived from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// Level the game started at
+    pub fn start_level(&self) -> u8 {
+        self.start_level
+    }
+    /// Rows cleared so far
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+    /// Current score
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+    /// Upcoming pieces, the next one first
+    pub fn next_pieces(&self) -> impl Iterator<Item = PieceType> + '_ {
+        self.queue.iter().copied()
+    }
+    /// How `piece` looks when it spawns with the current rotation system
+    pub fn spawn_shape(&self, piece: PieceType) -> PieceGrid {
+        self.rotation_system.shape(piece, PieceRotation::North)
+    }
+    /// The piece in the hold box
+    pub fn held_piece(&self) -> Option<PieceType> {
+        self.held
+    }
+    /// Whether hold is allowed, once per piece
+    pub fn can_hold(&self) -> bool {
+        !self.hold_used
+    }
+    /// The rules of the game
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+    /// Replace the ruleset, takes full effect on the next `restart`.
+    pub fn set_ruleset(&mut self, ruleset: Ruleset) {
+        self.ruleset = ruleset;
+        self.start_level = ruleset.gravity.clamp_start_level(self.start_level);
+    }
+    /// Choose the level the next game starts at, clamped to the start levels
+    /// of the gravity curve
+    pub fn set_start_level(&mut self, level: u8) {
+        self.start_level = self.ruleset.gravity.clamp_start_level(level);
+    }
+    /// Whether the stack topped out
     pub fn is_game_over(&self) -> bool {
         self.game_over
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use super::*;
+
+    fn set_piece(state: &mut TetrisState, piece: PieceType, rotation: PieceRotation, x: i8, y: i8) {
+        state.current = CurrentPiece {
+            piece,
+            x,
+            y,
+            rotation,
+            shape: state.rotation_system.shape(piece, rotation),
+        };
+    }
+
+    // Fill `row` except for the columns in `gap`
+    fn fill_row(state: &mut TetrisState, row: usize, gap: Range<usize>) {
+        for x in (0..FIELD_WIDTH).filter(|x| !gap.contains(x)) {
+            state.field.data[row][x] = BlockColor::Red;
+        }
+    }
+
+    // Hard drop a piece that already rests on the stack, so no drop points
+    // are scored, and return the points of the lock
+    fn lock_points(
+        state: &mut TetrisState,
+        piece: PieceType,
+        rotation: PieceRotation,
+        x: i8,
+        y: i8,
+    ) -> u32 {
+        set_piece(state, piece, rotation, x, y);
+        assert!(state.on_ground());
+        let score = state.score();
+        state.hard_drop();
+        state.score() - score
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let mut state = TetrisState::with_seed(1, Ruleset::default(), 1);
+        // A slot for a T pointing down in columns 3-5 of the two bottom
+        // rows, with an overhang over its right corner
+        for x in 0..FIELD_WIDTH {
+            if x != 4 {
+                state.field.data[19][x] = BlockColor::Red;
+            }
+            if !(3..=5).contains(&x) {
+                state.field.data[18][x] = BlockColor::Red;
+            }
+        }
+        state.field.data[17][5] = BlockColor::Red;
+        set_piece(&mut state, PieceType::T, PieceRotation::East, 3, 17);
+        state.take_events();
+
+        state.rotate_cw();
+        state.hard_drop();
+        let events = state.take_events();
+        assert!(events.contains(&GameEvent::TSpin(TSpin::Full)));
+        let clear = state.last_clear().unwrap();
+        assert_eq!(clear.rows, 2);
+        assert_eq!(clear.tspin, Some(TSpin::Full));
+        assert_eq!(state.lines(), 2);
+        // Guideline T-spin double at level 1
+        assert_eq!(state.level(), 1);
+        assert_eq!(state.score(), 1200);
+    }
+
+    #[test]
+    fn combo() {
+        let mut state = TetrisState::with_seed(1, Ruleset::default(), 1);
+        // Two rows that a flat I each clears, the block above keeps the
+        // playfield from being empty afterwards
+        fill_row(&mut state, 19, 0..4);
+        fill_row(&mut state, 18, 0..4);
+        state.field.data[17][9] = BlockColor::Red;
+
+        let points = lock_points(&mut state, PieceType::I, PieceRotation::North, 0, 18);
+        assert_eq!(points, 100);
+        assert_eq!(state.combo(), 0);
+        let points = lock_points(&mut state, PieceType::I, PieceRotation::North, 0, 18);
+        assert_eq!(points, 100 + 50);
+        assert_eq!(state.combo(), 1);
+        assert_eq!(state.last_clear().unwrap().combo, 1);
+        // Locking without a clear breaks the combo
+        let points = lock_points(&mut state, PieceType::I, PieceRotation::North, 0, 18);
+        assert_eq!(points, 0);
+        assert_eq!(state.combo(), 0);
+    }
+
+    #[test]
+    fn back_to_back_tetris() {
+        let mut state = TetrisState::with_seed(1, Ruleset::default(), 1);
+        // Eight rows with the first column open for two upright I pieces
+        for row in 12..FIELD_HEIGHT {
+            fill_row(&mut state, row, 0..1);
+        }
+        state.field.data[11][9] = BlockColor::Red;
+
+        let points = lock_points(&mut state, PieceType::I, PieceRotation::East, -2, 16);
+        assert_eq!(points, 800);
+        // A lock without a clear in between breaks the combo only
+        let points = lock_points(&mut state, PieceType::I, PieceRotation::North, 4, 14);
+        assert_eq!(points, 0);
+        let points = lock_points(&mut state, PieceType::I, PieceRotation::East, -2, 16);
+        assert_eq!(points, 800 * 3 / 2);
+        assert_eq!(state.back_to_back(), 1);
+        assert!(state.last_clear().unwrap().back_to_back);
+        assert_eq!(state.level(), 1);
+    }
+
+    #[test]
+    fn perfect_clear() {
+        let mut state = TetrisState::with_seed(1, Ruleset::default(), 1);
+        fill_row(&mut state, 19, 0..4);
+
+        let points = lock_points(&mut state, PieceType::I, PieceRotation::North, 0, 18);
+        assert_eq!(points, 100 + 800);
+        assert!(state.field.is_empty());
+        assert_eq!(state.perfect_clears(), 1);
+        let events = state.take_events();
+        assert!(events.iter().any(|event| matches!(
+            event,
+            GameEvent::LinesCleared { clear, .. } if clear.perfect_clear
+        )));
+    }
+
+    // Land the first piece on the floor, then shift it back and forth for
+    // `shifts` frames and count the frames until it locks
+    fn frames_until_lock(lock_reset: LockReset, shifts: u32) -> u32 {
+        let ruleset = Ruleset {
+            lock_reset,
+            ..Ruleset::default()
+        };
+        let mut state = TetrisState::with_seed(1, ruleset, 1);
+        while state.drop_one_line() {}
+        let mut frames = 0;
+        while state.pieces_placed() == 0 {
+            if frames < shifts {
+                if frames % 2 == 0 {
+                    state.move_left();
+                } else {
+                    state.move_right();
+                }
+            }
+            state.tick();
+            frames += 1;
+        }
+        frames
+    }
+
+    #[test]
+    fn lock_delay_without_moves() {
+        let lock_delay = Ruleset::default().lock_delay;
+        assert_eq!(lock_delay, 30);
+        for lock_reset in LockReset::ALL {
+            assert_eq!(frames_until_lock(lock_reset, 0), lock_delay);
+        }
+    }
+
+    #[test]
+    fn lock_delay_with_moves() {
+        // Every move restarts the delay, the frame of the last move counts
+        assert_eq!(frames_until_lock(LockReset::Infinite, 100), 99 + 30);
+        // Out of resets the piece locks at once
+        assert_eq!(
+            frames_until_lock(LockReset::MoveReset, 100),
+            MOVE_RESET_LIMIT
+        );
+        // Moves never restart the delay
+        assert_eq!(frames_until_lock(LockReset::StepReset, 100), 30);
+    }
+
+    #[test]
+    fn lock_out() {
+        let mut state = TetrisState::with_seed(1, Ruleset::default(), 1);
+        // An I lying on top of a stack that reaches the top row, the empty
+        // column keeps the row from clearing
+        for x in 1..FIELD_WIDTH {
+            state.field.data[0][x] = BlockColor::Red;
+        }
+        set_piece(&mut state, PieceType::I, PieceRotation::North, 3, -2);
+        assert!(state.current.blocks().all(|(_, y)| y == -1));
+        state.take_events();
+
+        state.hard_drop();
+        let events = state.take_events();
+        assert_eq!(events.last(), Some(&GameEvent::TopOut));
+        assert!(!events
+            .iter()
+            .any(|event| matches!(event, GameEvent::Spawned(_))));
+        assert!(state.is_game_over());
+        assert_eq!(state.pieces_placed(), 1);
+    }
+}
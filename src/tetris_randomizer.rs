@@ -0,0 +1,194 @@
+//! Piece randomizers, all driven by the seeded game RNG.
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::tetris_model::PieceType;
+
+/// The RNG owned by a game session. ChaCha8 produces the same sequence on
+/// every platform and rand version, so a seed reproduces a game exactly.
+pub type GameRng = ChaCha8Rng;
+
+/// A game RNG seeded with `seed`
+pub fn game_rng(seed: u64) -> GameRng {
+    GameRng::seed_from_u64(seed)
+}
+
+/// Source of the piece sequence, all randomness comes from the session RNG
+pub trait Randomizer {
+    /// The next piece of the sequence
+    fn next_piece(&mut self, rng: &mut GameRng) -> PieceType;
+    /// The pieces the randomizer remembers, for saving a game
+    fn state(&self) -> Vec<PieceType> {
+        Vec::new()
+    }
+    /// Continue from a `state` of the same kind of randomizer
+    fn set_state(&mut self, _state: &[PieceType]) {}
+}
+
+/// The available randomizers
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum RandomizerKind {
+    /// See [`PureRandom`]
+    Random,
+    /// [`BagRandomizer`] with one of each piece
+    #[default]
+    Bag7,
+    /// [`BagRandomizer`] with two of each piece
+    Bag14,
+    /// See [`NesRandomizer`]
+    Nes,
+    /// See [`TgmRandomizer`]
+    Tgm,
+}
+
+impl RandomizerKind {
+    /// Every kind, in menu order
+    pub const ALL: [RandomizerKind; 5] = [
+        RandomizerKind::Random,
+        RandomizerKind::Bag7,
+        RandomizerKind::Bag14,
+        RandomizerKind::Nes,
+        RandomizerKind::Tgm,
+    ];
+
+    /// A new randomizer of this kind
+    pub fn create(self) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::Random => Box::new(PureRandom),
+            RandomizerKind::Bag7 => Box::new(BagRandomizer::new(1)),
+            RandomizerKind::Bag14 => Box::new(BagRandomizer::new(2)),
+            RandomizerKind::Nes => Box::new(NesRandomizer::default()),
+            RandomizerKind::Tgm => Box::new(TgmRandomizer::default()),
+        }
+    }
+}
+
+fn random_piece(rng: &mut GameRng) -> PieceType {
+    PieceType::ALL[rng.random_range(0..PieceType::ALL.len())]
+}
+
+/// Every piece is equally likely every time
+pub struct PureRandom;
+
+impl Randomizer for PureRandom {
+    fn next_piece(&mut self, rng: &mut GameRng) -> PieceType {
+        random_piece(rng)
+    }
+}
+
+/// Deals a shuffled bag holding `copies` of each piece, refilled when empty
+pub struct BagRandomizer {
+    copies: usize,
+    bag: Vec<PieceType>,
+}
+
+impl BagRandomizer {
+    /// A bag with `copies` of each of the seven pieces
+    pub fn new(copies: usize) -> BagRandomizer {
+        BagRandomizer {
+            copies,
+            bag: Vec::new(),
+        }
+    }
+}
+
+impl Randomizer for BagRandomizer {
+    fn next_piece(&mut self, rng: &mut GameRng) -> PieceType {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend(PieceType::ALL);
+            }
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().unwrap()
+    }
+    fn state(&self) -> Vec<PieceType> {
+        self.bag.clone()
+    }
+    fn set_state(&mut self, state: &[PieceType]) {
+        self.bag = state.to_vec();
+    }
+}
+
+/// NES style, roll one of eight outcomes and roll again once when the
+/// outcome is the previous piece or the eighth "reroll" outcome
+#[derive(Default)]
+pub struct NesRandomizer {
+    previous: Option<PieceType>,
+}
+
+impl Randomizer for NesRandomizer {
+    fn next_piece(&mut self, rng: &mut GameRng) -> PieceType {
+        let roll = rng.random_range(0..=PieceType::ALL.len());
+        let piece = match PieceType::ALL.get(roll) {
+            Some(&piece) if Some(piece) != self.previous => piece,
+            _ => random_piece(rng),
+        };
+        self.previous = Some(piece);
+        piece
+    }
+    fn state(&self) -> Vec<PieceType> {
+        self.previous.into_iter().collect()
+    }
+    fn set_state(&mut self, state: &[PieceType]) {
+        self.previous = state.first().copied();
+    }
+}
+
+/// TGM style, remembers the last four pieces and rerolls a piece in that
+/// history up to six times. The first piece is never S, Z or O.
+pub struct TgmRandomizer {
+    history: [PieceType; 4],
+    first: bool,
+}
+
+impl TgmRandomizer {
+    const REROLLS: usize = 6;
+}
+
+impl Default for TgmRandomizer {
+    fn default() -> Self {
+        TgmRandomizer {
+            history: [PieceType::Z, PieceType::S, PieceType::S, PieceType::Z],
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for TgmRandomizer {
+    fn next_piece(&mut self, rng: &mut GameRng) -> PieceType {
+        let piece = if self.first {
+            self.first = false;
+            let first_pieces = [PieceType::I, PieceType::J, PieceType::L, PieceType::T];
+            first_pieces[rng.random_range(0..first_pieces.len())]
+        } else {
+            let mut piece = random_piece(rng);
+            for _ in 0..TgmRandomizer::REROLLS {
+                if !self.history.contains(&piece) {
+                    break;
+                }
+                piece = random_piece(rng);
+            }
+            piece
+        };
+        self.history.rotate_left(1);
+        self.history[3] = piece;
+        piece
+    }
+    // Nothing before the first piece, the history after that
+    fn state(&self) -> Vec<PieceType> {
+        if self.first {
+            Vec::new()
+        } else {
+            self.history.to_vec()
+        }
+    }
+    fn set_state(&mut self, state: &[PieceType]) {
+        *self = TgmRandomizer::default();
+        if let Ok(history) = state.try_into() {
+            self.history = history;
+            self.first = false;
+        }
+    }
+}
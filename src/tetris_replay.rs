@@ -0,0 +1,312 @@
+//! Recording games and playing them back.
+//!
+//! A game is fully determined by its seed, start level, ruleset and the
+//! inputs of each frame, so that is all a [`Replay`] keeps. It is written as
+//! a small text file:
+//!
+//! ```text
+//! rustris replay 1
+//! seed 1234
+//! level 1
+//! mode marathon
+//! ...
+//! inputs
+//! 40+L 6-L 31+C 0-C 12+D 0-D
+//! ```
+//!
+//! Each input is the number of frames since the previous input, `+` for a
+//! press or `-` for a release and a letter for the action.
+
+use std::{error::Error, fmt, str::FromStr};
+
+use crate::tetris_event::GameEvent;
+use crate::tetris_format::{name_of, parse_name, read_settings, set_ruleset, write_ruleset};
+use crate::tetris_input::{Action, Input};
+use crate::tetris_model::{GameMode, Ruleset, TetrisState};
+
+pub(crate) const FILE_HEADER: &str = "rustris replay";
+/// Version of the replay file format, bumped when old files can no longer
+/// be played the same way
+pub const REPLAY_VERSION: u32 = 1;
+const INPUTS_PER_LINE: usize = 16;
+
+const ACTION_LETTERS: [(Action, &str); 8] = [
+    (Action::MoveLeft, "L"),
+    (Action::MoveRight, "R"),
+    (Action::SoftDrop, "S"),
+    (Action::HardDrop, "D"),
+    (Action::RotateCw, "C"),
+    (Action::RotateCcw, "W"),
+    (Action::Rotate180, "F"),
+    (Action::Hold, "H"),
+];
+
+/// Why a replay could not be read
+#[derive(Debug, PartialEq)]
+pub enum ReplayError {
+    /// The text is not a replay
+    NotAReplay,
+    /// The replay was written by another version of the format
+    Version(u32),
+    /// A line that could not be understood
+    Malformed(String),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::NotAReplay => write!(f, "not a rustris replay"),
+            ReplayError::Version(version) => write!(
+                f,
+                "replay version {} is not supported, expected {}",
+                version, REPLAY_VERSION
+            ),
+            ReplayError::Malformed(line) => write!(f, "malformed replay line: {}", line),
+        }
+    }
+}
+
+impl Error for ReplayError {}
+
+/// Everything needed to play a game again: its seed, start level, ruleset
+/// and the inputs with the frame they were given on
+#[derive(Clone)]
+pub struct Replay {
+    seed: u64,
+    level: u8,
+    ruleset: Ruleset,
+    inputs: Vec<(u64, Input)>,
+}
+
+impl Replay {
+    /// An empty recording of a game that has not started yet
+    pub fn new(tetris_state: &TetrisState) -> Replay {
+        Replay {
+            seed: tetris_state.seed(),
+            level: tetris_state.start_level(),
+            ruleset: tetris_state.ruleset(),
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Add the inputs given to `TetrisState::step` on `frame`, frames must
+    /// be recorded in order
+    pub fn record(&mut self, frame: u64, inputs: &[Input]) {
+        self.inputs
+            .extend(inputs.iter().map(|&input| (frame, input)));
+    }
+
+    /// Seed of the game
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Rules of the game
+    pub fn ruleset(&self) -> Ruleset {
+        self.ruleset
+    }
+
+    /// The recorded inputs and the frame each was given on
+    pub fn inputs(&self) -> &[(u64, Input)] {
+        &self.inputs
+    }
+
+    /// The game as it was before the first frame
+    pub fn start(&self) -> TetrisState {
+        TetrisState::with_seed(self.level, self.ruleset, self.seed)
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut settings = String::new();
+        write_ruleset(&mut settings, &self.ruleset);
+        writeln!(f, "{} {}", FILE_HEADER, REPLAY_VERSION)?;
+        writeln!(f, "seed {}", self.seed)?;
+        writeln!(f, "level {}", self.level)?;
+        write!(f, "{}", settings)?;
+        writeln!(f, "inputs")?;
+
+        let mut previous = 0;
+        for line in self.inputs.chunks(INPUTS_PER_LINE) {
+            let tokens: Vec<String> = line
+                .iter()
+                .map(|&(frame, input)| {
+                    let (sign, action) = match input {
+                        Input::Press(action) => ('+', action),
+                        Input::Release(action) => ('-', action),
+                    };
+                    let delta = frame - previous;
+                    previous = frame;
+                    format!("{}{}{}", delta, sign, name_of(&ACTION_LETTERS, action))
+                })
+                .collect();
+            writeln!(f, "{}", tokens.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    /// Read a replay, settings that are left out keep the values of the
+    /// mode's preset
+    fn from_str(content: &str) -> Result<Replay, ReplayError> {
+        let mut lines = content.lines();
+        let version = lines
+            .next()
+            .and_then(|line| line.strip_prefix(FILE_HEADER))
+            .ok_or(ReplayError::NotAReplay)?;
+        let version = version
+            .trim()
+            .parse()
+            .map_err(|_| ReplayError::NotAReplay)?;
+        if version != REPLAY_VERSION {
+            return Err(ReplayError::Version(version));
+        }
+
+        let settings = read_settings(&mut lines.by_ref(), "inputs")
+            .map_err(|line| ReplayError::Malformed(line.to_string()))?;
+
+        let mut seed = None;
+        let mut level = 0;
+        let mut ruleset = Ruleset::default();
+        // The mode comes first so the other settings override its preset
+        if let Some(&(_, value)) = settings.iter().find(|(key, _)| *key == "mode") {
+            ruleset = GameMode::from_id(value)
+                .ok_or_else(|| ReplayError::Malformed(format!("mode {}", value)))?
+                .ruleset();
+        }
+        for (key, value) in settings {
+            let malformed = || ReplayError::Malformed(format!("{} {}", key, value));
+            match key {
+                "seed" => seed = Some(value.parse().map_err(|_| malformed())?),
+                "level" => level = value.parse().map_err(|_| malformed())?,
+                _ => set_ruleset(&mut ruleset, key, value).ok_or_else(malformed)?,
+            }
+        }
+
+        let mut inputs = Vec::new();
+        let mut frame: u64 = 0;
+        for token in lines.flat_map(str::split_whitespace) {
+            let malformed = || ReplayError::Malformed(token.to_string());
+            let sign = token.find(['+', '-']).ok_or_else(malformed)?;
+            let delta: u64 = token[..sign].parse().map_err(|_| malformed())?;
+            let action = parse_name(&ACTION_LETTERS, &token[sign + 1..]).ok_or_else(malformed)?;
+            frame = frame.checked_add(delta).ok_or_else(malformed)?;
+            let input = if token[sign..].starts_with('+') {
+                Input::Press(action)
+            } else {
+                Input::Release(action)
+            };
+            inputs.push((frame, input));
+        }
+
+        Ok(Replay {
+            seed: seed.ok_or(ReplayError::Malformed("missing seed".to_string()))?,
+            level,
+            ruleset,
+            inputs,
+        })
+    }
+}
+
+/// Plays a replay one frame at a time
+pub struct Playback {
+    replay: Replay,
+    tetris_state: TetrisState,
+    next_input: usize,
+}
+
+impl Playback {
+    /// Start playing `replay` from its first frame
+    pub fn new(replay: Replay) -> Playback {
+        Playback {
+            tetris_state: replay.start(),
+            replay,
+            next_input: 0,
+        }
+    }
+
+    /// The game as far as it has been played
+    pub fn state(&self) -> &TetrisState {
+        &self.tetris_state
+    }
+
+    /// The replay being played
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Frames played so far
+    pub fn frame(&self) -> u64 {
+        self.tetris_state.frames()
+    }
+
+    /// Whether the game has ended
+    pub fn is_finished(&self) -> bool {
+        self.tetris_state.is_game_over()
+    }
+
+    /// Play the next frame with the inputs recorded for it
+    pub fn step(&mut self) -> Vec<GameEvent> {
+        let frame = self.frame();
+        let first = self.next_input;
+        let inputs = &self.replay.inputs;
+        while inputs
+            .get(self.next_input)
+            .is_some_and(|&(input_frame, _)| input_frame <= frame)
+        {
+            self.next_input += 1;
+        }
+        let frame_inputs: Vec<Input> = inputs[first..self.next_input]
+            .iter()
+            .map(|&(_, input)| input)
+            .collect();
+        self.tetris_state.step(&frame_inputs)
+    }
+
+    /// Jump to `frame`, or to the end if the game is over before that.
+    /// Going back plays the replay again from the start. A game can end
+    /// on the input of a frame without playing the frame, `u64::MAX` always
+    /// goes to the very end.
+    pub fn seek(&mut self, frame: u64) {
+        if frame < self.frame() {
+            self.tetris_state = self.replay.start();
+            self.next_input = 0;
+        }
+        while self.frame() < frame && !self.is_finished() {
+            self.step();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris_format::tests::inputs;
+
+    #[test]
+    fn parsed_replay_plays_the_same_game() {
+        for mode in GameMode::ALL {
+            let mut state = TetrisState::with_seed(3, mode.ruleset(), 7);
+            let mut replay = Replay::new(&state);
+            while !state.is_game_over() {
+                let inputs = inputs(state.frames());
+                replay.record(state.frames(), &inputs);
+                state.step(&inputs);
+            }
+
+            let text = replay.to_string();
+            let parsed: Replay = text.parse().unwrap();
+            assert_eq!(parsed.to_string(), text);
+            let mut playback = Playback::new(parsed);
+            playback.seek(u64::MAX);
+            assert!(playback.is_finished());
+            assert_eq!(playback.frame(), state.frames());
+            assert_eq!(playback.state().score(), state.score());
+            assert_eq!(playback.state().save(), state.save());
+        }
+    }
+}
@@ -0,0 +1,288 @@
+//! Rotation systems, piece shapes per rotation state and their kicks.
+
+use crate::tetris_model::{PieceGrid, PieceRotation, PieceType};
+
+/// How pieces are shaped in each rotation state and which offsets are tried
+/// when a rotation is blocked.
+pub trait RotationSystem {
+    /// Blocks of `piece` in `rotation` inside its rotation box
+    fn shape(&self, piece: PieceType, rotation: PieceRotation) -> PieceGrid;
+    /// Offsets (x, y) to try in order when rotating `from` -> `to`, the first
+    /// one is normally (0, 0). Positive y moves the piece up.
+    fn kicks(
+        &self,
+        piece: PieceType,
+        from: PieceRotation,
+        to: PieceRotation,
+    ) -> &'static [(i8, i8)];
+    /// Called when the unkicked rotation is obstructed, `blocked` is the
+    /// first obstructed cell (x, y) of the rotation box in reading order.
+    fn allow_kick(
+        &self,
+        _piece: PieceType,
+        _from: PieceRotation,
+        _blocked: (usize, usize),
+    ) -> bool {
+        true
+    }
+}
+
+/// The available rotation systems
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum RotationSystemKind {
+    /// See [`Srs`]
+    #[default]
+    Srs,
+    /// See [`Ars`]
+    Ars,
+    /// See [`Nes`]
+    Nes,
+}
+
+impl RotationSystemKind {
+    /// Every kind, in menu order
+    pub const ALL: [RotationSystemKind; 3] = [
+        RotationSystemKind::Srs,
+        RotationSystemKind::Ars,
+        RotationSystemKind::Nes,
+    ];
+
+    /// A new rotation system of this kind
+    pub fn create(self) -> Box<dyn RotationSystem> {
+        match self {
+            RotationSystemKind::Srs => Box::new(Srs),
+            RotationSystemKind::Ars => Box::new(Ars),
+            RotationSystemKind::Nes => Box::new(Nes),
+        }
+    }
+}
+
+const NO_KICKS: [(i8, i8); 1] = [(0, 0)];
+
+// SRS kick tables indexed by the rotation being made, y points up
+#[rustfmt::skip]
+const SRS_JLSTZ_KICKS: [[(i8, i8); 5]; 8] = [
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 0 -> R
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 0
+    [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],     // R -> 2
+    [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)], // 2 -> R
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 2 -> L
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 2
+    [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],  // L -> 0
+    [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],    // 0 -> L
+];
+
+#[rustfmt::skip]
+const SRS_I_KICKS: [[(i8, i8); 5]; 8] = [
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],   // 0 -> R
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],   // R -> 0
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],   // R -> 2
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],   // 2 -> R
+    [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],   // 2 -> L
+    [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],   // L -> 2
+    [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],   // L -> 0
+    [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],   // 0 -> L
+];
+
+// 180 degree kicks as used by modern guideline clients, shared by all
+// pieces except O, y points up
+#[rustfmt::skip]
+const SRS_180_KICKS: [[(i8, i8); 6]; 4] = [
+    [(0, 0), (0, 1), (1, 1), (-1, 1), (1, 0), (-1, 0)],     // 0 -> 2
+    [(0, 0), (1, 0), (1, 2), (1, 1), (0, 2), (0, 1)],       // R -> L
+    [(0, 0), (0, -1), (-1, -1), (1, -1), (-1, 0), (1, 0)],  // 2 -> 0
+    [(0, 0), (-1, 0), (-1, 2), (-1, 1), (0, 2), (0, 1)],    // L -> R
+];
+
+const ARS_KICKS: [(i8, i8); 3] = [(0, 0), (1, 0), (-1, 0)];
+
+/// Index into the SRS kick tables, None for rotations not in the tables
+fn srs_kick_index(from: PieceRotation, to: PieceRotation) -> Option<usize> {
+    use PieceRotation::*;
+    match (from, to) {
+        (North, East) => Some(0),
+        (East, North) => Some(1),
+        (East, South) => Some(2),
+        (South, East) => Some(3),
+        (South, West) => Some(4),
+        (West, South) => Some(5),
+        (West, North) => Some(6),
+        (North, West) => Some(7),
+        _ => None,
+    }
+}
+
+/// Rotate `grid` clockwise `rotation` quarter turns inside a `size` box
+fn rotate_grid(grid: PieceGrid, size: usize, rotation: PieceRotation) -> PieceGrid {
+    let mut rotated = grid;
+    for _ in 0..rotation as usize {
+        let previous = rotated;
+        for (y, row) in rotated.iter_mut().enumerate().take(size) {
+            for (x, block) in row.iter_mut().enumerate().take(size) {
+                *block = previous[size - 1 - x][y];
+            }
+        }
+    }
+    rotated
+}
+
+/// Move the blocks down until they rest on the bottom of a `size` box
+fn bottom_align(grid: PieceGrid, size: usize) -> PieceGrid {
+    let mut aligned = grid;
+    while !aligned[size - 1].iter().any(|&block| block) {
+        aligned.copy_within(0..size - 1, 1);
+        aligned[0] = Default::default();
+    }
+    aligned
+}
+
+/// Two state pieces only use North and one of the vertical states
+fn two_state(rotation: PieceRotation, vertical: PieceRotation) -> PieceRotation {
+    match rotation {
+        PieceRotation::North | PieceRotation::South => PieceRotation::North,
+        PieceRotation::East | PieceRotation::West => vertical,
+    }
+}
+
+/// Super Rotation System, the guideline rotation system. Pieces spawn flat
+/// side down and rotate around the center of their box, with wall and floor
+/// kicks from the SRS tables.
+pub struct Srs;
+
+impl RotationSystem for Srs {
+    fn shape(&self, piece: PieceType, rotation: PieceRotation) -> PieceGrid {
+        rotate_grid(piece.shape(), piece.box_size(), rotation)
+    }
+    fn kicks(
+        &self,
+        piece: PieceType,
+        from: PieceRotation,
+        to: PieceRotation,
+    ) -> &'static [(i8, i8)] {
+        if piece == PieceType::O {
+            return &NO_KICKS;
+        }
+        if to == from.cw().cw() {
+            return &SRS_180_KICKS[from as usize];
+        }
+        let Some(index) = srs_kick_index(from, to) else {
+            return &NO_KICKS;
+        };
+        match piece {
+            PieceType::I => &SRS_I_KICKS[index],
+            _ => &SRS_JLSTZ_KICKS[index],
+        }
+    }
+}
+
+/// Arika Rotation System from the TGM series. J, L and T spawn flat side
+/// up, pieces rest on the bottom of their box and I, S and Z only have two
+/// states. A blocked rotation tries one step right then one step left,
+/// except for I and when the center column rule applies.
+pub struct Ars;
+
+impl RotationSystem for Ars {
+    fn shape(&self, piece: PieceType, rotation: PieceRotation) -> PieceGrid {
+        let size = piece.box_size();
+        match piece {
+            PieceType::O => piece.shape(),
+            PieceType::I => rotate_grid(
+                piece.shape(),
+                size,
+                two_state(rotation, PieceRotation::East),
+            ),
+            PieceType::S | PieceType::Z => {
+                let rotation = two_state(rotation, PieceRotation::West);
+                bottom_align(rotate_grid(piece.shape(), size, rotation), size)
+            }
+            _ => {
+                let rotation = rotation.cw().cw();
+                bottom_align(rotate_grid(piece.shape(), size, rotation), size)
+            }
+        }
+    }
+    fn kicks(
+        &self,
+        piece: PieceType,
+        _from: PieceRotation,
+        _to: PieceRotation,
+    ) -> &'static [(i8, i8)] {
+        match piece {
+            PieceType::I | PieceType::O => &NO_KICKS,
+            _ => &ARS_KICKS,
+        }
+    }
+    fn allow_kick(&self, piece: PieceType, from: PieceRotation, blocked: (usize, usize)) -> bool {
+        // Center column rule, J, L and T leaving the spawn or flipped state
+        // may not kick when the first obstruction is in the middle column
+        let center_rule = matches!(piece, PieceType::J | PieceType::L | PieceType::T)
+            && matches!(from, PieceRotation::North | PieceRotation::South);
+        !(center_rule && blocked.0 == 1)
+    }
+}
+
+/// Classic NES rotation, pieces turn around their center block without any
+/// kicks. J, L and T spawn flat side up and I, S and Z only have two states.
+pub struct Nes;
+
+impl RotationSystem for Nes {
+    fn shape(&self, piece: PieceType, rotation: PieceRotation) -> PieceGrid {
+        let size = piece.box_size();
+        match piece {
+            PieceType::O => piece.shape(),
+            PieceType::I => rotate_grid(
+                piece.shape(),
+                size,
+                two_state(rotation, PieceRotation::East),
+            ),
+            PieceType::S | PieceType::Z => {
+                // Spawn one row lower so the center block is the box center
+                let spawn = bottom_align(piece.shape(), size);
+                rotate_grid(spawn, size, two_state(rotation, PieceRotation::West))
+            }
+            _ => rotate_grid(piece.shape(), size, rotation.cw().cw()),
+        }
+    }
+    fn kicks(
+        &self,
+        _piece: PieceType,
+        _from: PieceRotation,
+        _to: PieceRotation,
+    ) -> &'static [(i8, i8)] {
+        &NO_KICKS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris_event::GameEvent;
+    use crate::tetris_model::{CurrentPiece, Ruleset, TetrisState};
+
+    #[test]
+    fn srs_kicks_a_t_off_the_left_wall() {
+        let mut state = TetrisState::with_seed(1, Ruleset::default(), 1);
+        // T pointing right with its stem in the first column, the box
+        // sticks out of the playfield
+        state.current = CurrentPiece {
+            piece: PieceType::T,
+            x: -1,
+            y: 5,
+            rotation: PieceRotation::East,
+            shape: Srs.shape(PieceType::T, PieceRotation::East),
+        };
+        state.take_events();
+        state.rotate_ccw();
+        assert_eq!(
+            state.take_events(),
+            [GameEvent::Rotated {
+                from: PieceRotation::East,
+                to: PieceRotation::North,
+                kick: 1,
+                offset: (1, 0),
+            }]
+        );
+        assert_eq!(state.current_piece().x(), 0);
+        assert_eq!(state.current_piece().rotation(), PieceRotation::North);
+    }
+}
@@ -0,0 +1,307 @@
+//! Saving a game in progress and continuing it later.
+//!
+//! [`TetrisState::save`] writes everything the game needs to continue
+//! exactly where it was as `key value` lines, the ruleset first and the
+//! playfield last:
+//!
+//! ```text
+//! rustris save 1
+//! mode marathon
+//! ...
+//! score 1200
+//! current T 4 17 N
+//! queue SZL
+//! ...
+//! field
+//! ..........
+//! ```
+
+use std::{collections::HashMap, error::Error, fmt, str::FromStr};
+
+use crate::tetris_format::{
+    name_of, parse_name, parse_pieces, pieces_to_string, read_settings, set_ruleset, write_ruleset,
+    BLOCK_NAMES, PIECE_NAMES, ROTATION_STATE_NAMES, TSPIN_NAMES,
+};
+use crate::tetris_model::{
+    CurrentPiece, LastMove, LineClear, Ruleset, TetrisState, FIELD_HEIGHT, FIELD_WIDTH,
+    MAX_PREVIEW, MIN_PREVIEW,
+};
+
+pub(crate) const SAVE_HEADER: &str = "rustris save";
+/// Version of the saved game format, saves of other versions can not be
+/// continued
+pub const SAVE_VERSION: u32 = 1;
+
+/// Why a saved game could not be read
+#[derive(Debug, PartialEq)]
+pub enum SaveError {
+    /// The text is not a saved game
+    NotASave,
+    /// The game was saved with another version of the format
+    Version(u32),
+    /// A setting that is missing or could not be understood
+    Malformed(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SaveError::NotASave => write!(f, "not a rustris saved game"),
+            SaveError::Version(version) => write!(
+                f,
+                "saved game version {} is not supported, expected {}",
+                version, SAVE_VERSION
+            ),
+            SaveError::Malformed(setting) => write!(f, "malformed saved game: {}", setting),
+        }
+    }
+}
+
+impl Error for SaveError {}
+
+impl TetrisState {
+    /// The game as text that `load` continues exactly where it was. Keys
+    /// held down are not saved, the game continues with every key up.
+    pub fn save(&self) -> String {
+        let mut text = format!("{} {}\n", SAVE_HEADER, SAVE_VERSION);
+        write_ruleset(&mut text, &self.ruleset);
+        let current = &self.current;
+        let last_move = match self.last_move {
+            LastMove::Spawn => "spawn".to_string(),
+            LastMove::Shift => "shift".to_string(),
+            LastMove::Rotation { kick, half_turn } => format!("rotation {} {}", kick, half_turn),
+        };
+        let last_clear = match self.last_clear {
+            Some(clear) => format!(
+                "{} {} {} {} {} {}",
+                clear.rows,
+                clear
+                    .tspin
+                    .map_or("none", |tspin| name_of(&TSPIN_NAMES, tspin)),
+                clear.combo,
+                clear.back_to_back,
+                clear.perfect_clear,
+                clear.frame
+            ),
+            None => "-".to_string(),
+        };
+        let settings = [
+            ("seed", self.seed.to_string()),
+            ("rng", self.rng.get_word_pos().to_string()),
+            (
+                "randomizer_state",
+                pieces_to_string(self.randomizer.state().into_iter()),
+            ),
+            ("level", self.level.to_string()),
+            ("start_level", self.start_level.to_string()),
+            ("lines", self.lines.to_string()),
+            ("score", self.score.to_string()),
+            ("frames", self.frames.to_string()),
+            ("pieces", self.pieces.to_string()),
+            (
+                "current",
+                format!(
+                    "{} {} {} {}",
+                    name_of(&PIECE_NAMES, current.piece),
+                    current.x,
+                    current.y,
+                    name_of(&ROTATION_STATE_NAMES, current.rotation)
+                ),
+            ),
+            ("queue", pieces_to_string(self.queue.iter().copied())),
+            ("held", pieces_to_string(self.held.into_iter())),
+            ("hold_used", self.hold_used.to_string()),
+            ("gravity_counter", self.gravity_counter.to_string()),
+            ("lock_timer", self.lock_timer.to_string()),
+            ("move_resets", self.move_resets.to_string()),
+            ("lowest_y", self.lowest_y.to_string()),
+            ("last_move", last_move),
+            ("last_clear", last_clear),
+            ("clear_streak", self.clear_streak.to_string()),
+            ("difficult_streak", self.difficult_streak.to_string()),
+            ("perfect_clears", self.perfect_clears.to_string()),
+            ("soft_drop_was_held", self.soft_drop_was_held.to_string()),
+        ];
+        for (key, value) in settings {
+            text.push_str(&format!("{} {}\n", key, value));
+        }
+        text.push_str("field\n");
+        for row in self.field.data() {
+            let row: String = row
+                .iter()
+                .map(|&color| name_of(&BLOCK_NAMES, color))
+                .collect();
+            text.push_str(&row);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Continue a game from the text of `save`
+    pub fn load(text: &str) -> Result<TetrisState, SaveError> {
+        let mut lines = text.lines();
+        let version = lines
+            .next()
+            .and_then(|line| line.strip_prefix(SAVE_HEADER))
+            .ok_or(SaveError::NotASave)?;
+        let version = version.trim().parse().map_err(|_| SaveError::NotASave)?;
+        if version != SAVE_VERSION {
+            return Err(SaveError::Version(version));
+        }
+        let settings = read_settings(&mut lines, "field")
+            .map_err(|line| SaveError::Malformed(line.to_string()))?;
+
+        let mut ruleset = Ruleset::default();
+        let mut values = HashMap::new();
+        for (key, value) in settings {
+            if set_ruleset(&mut ruleset, key, value).is_none() {
+                values.insert(key, value);
+            }
+        }
+        let value = |key: &str| {
+            values
+                .get(key)
+                .copied()
+                .ok_or_else(|| SaveError::Malformed(format!("missing {}", key)))
+        };
+        let malformed =
+            |key: &str| SaveError::Malformed(format!("{} {}", key, value(key).unwrap_or("")));
+
+        let seed = parse_value(&values, "seed")?;
+        let mut state = TetrisState::with_seed(0, ruleset, seed);
+        state.rng.set_word_pos(parse_value(&values, "rng")?);
+        let randomizer_state = parse_pieces(value("randomizer_state")?)
+            .ok_or_else(|| malformed("randomizer_state"))?;
+        state.randomizer.set_state(&randomizer_state);
+        state.level = parse_value(&values, "level")?;
+        state.start_level = parse_value(&values, "start_level")?;
+        state.lines = parse_value(&values, "lines")?;
+        state.score = parse_value(&values, "score")?;
+        state.frames = parse_value(&values, "frames")?;
+        state.pieces = parse_value(&values, "pieces")?;
+
+        let current: Vec<&str> = value("current")?.split(' ').collect();
+        let [piece, x, y, rotation] = current[..] else {
+            return Err(malformed("current"));
+        };
+        let piece = parse_name(&PIECE_NAMES, piece).ok_or_else(|| malformed("current"))?;
+        let rotation =
+            parse_name(&ROTATION_STATE_NAMES, rotation).ok_or_else(|| malformed("current"))?;
+        state.current = CurrentPiece {
+            piece,
+            x: x.parse().map_err(|_| malformed("current"))?,
+            y: y.parse().map_err(|_| malformed("current"))?,
+            rotation,
+            shape: state.rotation_system.shape(piece, rotation),
+        };
+
+        state.queue = parse_pieces(value("queue")?)
+            .ok_or_else(|| malformed("queue"))?
+            .into();
+        if state.queue.len() != ruleset.preview.clamp(MIN_PREVIEW, MAX_PREVIEW) {
+            return Err(malformed("queue"));
+        }
+        state.held = parse_pieces(value("held")?)
+            .ok_or_else(|| malformed("held"))?
+            .first()
+            .copied();
+        state.hold_used = parse_value(&values, "hold_used")?;
+        state.gravity_counter = parse_value(&values, "gravity_counter")?;
+        state.lock_timer = parse_value(&values, "lock_timer")?;
+        state.move_resets = parse_value(&values, "move_resets")?;
+        state.lowest_y = parse_value(&values, "lowest_y")?;
+
+        let last_move: Vec<&str> = value("last_move")?.split(' ').collect();
+        state.last_move = match last_move[..] {
+            ["spawn"] => LastMove::Spawn,
+            ["shift"] => LastMove::Shift,
+            ["rotation", kick, half_turn] => LastMove::Rotation {
+                kick: kick.parse().map_err(|_| malformed("last_move"))?,
+                half_turn: half_turn.parse().map_err(|_| malformed("last_move"))?,
+            },
+            _ => return Err(malformed("last_move")),
+        };
+
+        let last_clear: Vec<&str> = value("last_clear")?.split(' ').collect();
+        state.last_clear = match last_clear[..] {
+            ["-"] => None,
+            [rows, tspin, combo, back_to_back, perfect_clear, frame] => Some(LineClear {
+                rows: rows.parse().map_err(|_| malformed("last_clear"))?,
+                tspin: match tspin {
+                    "none" => None,
+                    _ => Some(
+                        parse_name(&TSPIN_NAMES, tspin).ok_or_else(|| malformed("last_clear"))?,
+                    ),
+                },
+                combo: combo.parse().map_err(|_| malformed("last_clear"))?,
+                back_to_back: back_to_back.parse().map_err(|_| malformed("last_clear"))?,
+                perfect_clear: perfect_clear.parse().map_err(|_| malformed("last_clear"))?,
+                frame: frame.parse().map_err(|_| malformed("last_clear"))?,
+            }),
+            _ => return Err(malformed("last_clear")),
+        };
+        state.clear_streak = parse_value(&values, "clear_streak")?;
+        state.difficult_streak = parse_value(&values, "difficult_streak")?;
+        state.perfect_clears = parse_value(&values, "perfect_clears")?;
+        state.soft_drop_was_held = parse_value(&values, "soft_drop_was_held")?;
+
+        let mut rows = 0;
+        for (y, line) in lines.take(FIELD_HEIGHT).enumerate() {
+            let row = &mut state.field.data[y];
+            if line.chars().count() != FIELD_WIDTH {
+                return Err(SaveError::Malformed(line.to_string()));
+            }
+            for (cell, c) in row.iter_mut().zip(line.chars()) {
+                *cell = parse_name(&BLOCK_NAMES, c.encode_utf8(&mut [0; 4]))
+                    .ok_or_else(|| SaveError::Malformed(line.to_string()))?;
+            }
+            rows += 1;
+        }
+        if rows != FIELD_HEIGHT || !state.field.try_piece(state.current) {
+            return Err(SaveError::Malformed("field".to_string()));
+        }
+        state.events.clear();
+        Ok(state)
+    }
+}
+
+/// The value of `key` in a saved game, parsed
+fn parse_value<T: FromStr>(values: &HashMap<&str, &str>, key: &str) -> Result<T, SaveError> {
+    let value = values
+        .get(key)
+        .ok_or_else(|| SaveError::Malformed(format!("missing {}", key)))?;
+    value
+        .parse()
+        .map_err(|_| SaveError::Malformed(format!("{} {}", key, value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris_format::tests::inputs;
+    use crate::tetris_model::GameMode;
+
+    fn game_in_progress(mode: GameMode) -> TetrisState {
+        let mut state = TetrisState::with_seed(1, mode.ruleset(), 42);
+        for frame in 0..500 {
+            state.step(&inputs(frame));
+        }
+        state.release_all();
+        state
+    }
+
+    #[test]
+    fn loaded_game_continues_exactly() {
+        for mode in GameMode::ALL {
+            let mut state = game_in_progress(mode);
+            assert!(!state.is_game_over());
+            let text = state.save();
+            let mut loaded = TetrisState::load(&text).unwrap();
+            assert_eq!(loaded.save(), text);
+            for frame in 500..1500 {
+                assert_eq!(loaded.step(&inputs(frame)), state.step(&inputs(frame)));
+            }
+            assert_eq!(loaded.save(), state.save());
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::{error::Error, fs, io, path::PathBuf};
+
+use crate::data_dir;
+use rustris::tetris_model::TetrisState;
+use rustris::tetris_replay::Replay;
+
+const FILE_NAME: &str = "savegame.txt";
+// The replay of the game so far follows the game itself in the file
+const REPLAY_HEADER: &str = "\nrustris replay";
+
+/// A game that was quit before it ended, kept in the XDG data directory
+/// until it is continued
+pub struct SavedGame {
+    pub tetris_state: TetrisState,
+    pub recording: Replay,
+}
+
+/// What was found in the save file
+pub enum SaveSlot {
+    Empty,
+    Game(Box<SavedGame>),
+    /// A save that can not be continued, e.g. from another version
+    Unreadable(String),
+}
+
+impl SaveSlot {
+    pub fn load() -> SaveSlot {
+        let Ok(content) = file_path().and_then(fs::read_to_string) else {
+            return SaveSlot::Empty;
+        };
+        match parse(&content) {
+            Ok(saved_game) => SaveSlot::Game(Box::new(saved_game)),
+            Err(err) => SaveSlot::Unreadable(err.to_string()),
+        }
+    }
+
+    pub fn can_continue(&self) -> bool {
+        matches!(self, SaveSlot::Game(_))
+    }
+
+    /// Take the saved game out of the slot and remove the save file, a game
+    /// is only continued once
+    pub fn take(&mut self) -> Option<SavedGame> {
+        match std::mem::replace(self, SaveSlot::Empty) {
+            SaveSlot::Game(saved_game) => {
+                if let Ok(path) = file_path() {
+                    let _ = fs::remove_file(path);
+                }
+                Some(*saved_game)
+            }
+            slot => {
+                *self = slot;
+                None
+            }
+        }
+    }
+}
+
+pub fn save_game(tetris_state: &TetrisState, recording: &Replay) -> io::Result<()> {
+    let path = file_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, format!("{}{}", tetris_state.save(), recording))
+}
+
+fn parse(content: &str) -> Result<SavedGame, Box<dyn Error>> {
+    let (game, replay) = match content.find(REPLAY_HEADER) {
+        Some(index) => content.split_at(index + 1),
+        None => (content, ""),
+    };
+    Ok(SavedGame {
+        tetris_state: TetrisState::load(game)?,
+        recording: replay.parse()?,
+    })
+}
+
+/// `savegame.txt` in the data directory
+fn file_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join(FILE_NAME))
+}
@@ -0,0 +1,165 @@
+use std::{
+    error::Error,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::data_dir;
+use rustris::tetris_model::{TetrisState, FRAMES_PER_SECOND};
+use rustris::tetris_replay::{Playback, Replay};
+
+/// Playback speeds, in steps of two
+pub const SPEEDS: [f64; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+/// How far one seek jumps
+const SEEK_FRAMES: u64 = 5 * FRAMES_PER_SECOND as u64;
+/// A replay whose game does not end this long after its last input, e.g.
+/// with a lock delay that never runs out, is cut off there
+const MAX_FRAMES_AFTER_INPUT: u64 = 10 * 60 * FRAMES_PER_SECOND as u64;
+
+/// The replay of the last finished game, overwritten by the next one
+pub const LAST_REPLAY: &str = "last";
+const EXTENSION: &str = "replay";
+/// Replays are kept in this directory of the data directory
+const REPLAY_DIR: &str = "replays";
+
+/// Watching a replay with play/pause, speed control, seeking and stepping
+/// one frame at a time
+pub struct ReplayViewer {
+    playback: Playback,
+    /// Frames in the whole replay
+    length: u64,
+    /// Frame to seek to for the end, `u64::MAX` when the game ends
+    end: u64,
+    speed: usize,
+    paused: bool,
+    /// Fraction of a frame left over at slow speeds
+    partial_frame: f64,
+}
+
+impl ReplayViewer {
+    pub fn new(replay: Replay) -> ReplayViewer {
+        let mut playback = Playback::new(replay.clone());
+        let last_input = replay.inputs().last().map_or(0, |&(frame, _)| frame);
+        playback.seek(last_input + MAX_FRAMES_AFTER_INPUT);
+        if playback.is_finished() {
+            // The game can end on the inputs of a frame without playing it
+            playback.seek(u64::MAX);
+        }
+        ReplayViewer {
+            length: playback.frame(),
+            end: if playback.is_finished() {
+                u64::MAX
+            } else {
+                playback.frame()
+            },
+            playback: Playback::new(replay),
+            speed: NORMAL_SPEED,
+            paused: false,
+            partial_frame: 0.0,
+        }
+    }
+
+    /// Move on by `frames` frames of wall clock time
+    pub fn advance(&mut self, frames: u32) {
+        if self.paused {
+            return;
+        }
+        self.partial_frame += frames as f64 * SPEEDS[self.speed];
+        while self.partial_frame >= 1.0 && !self.is_at_end() {
+            self.partial_frame -= 1.0;
+            self.playback.step();
+        }
+        if self.is_at_end() {
+            self.paused = true;
+            self.partial_frame = 0.0;
+        }
+    }
+
+    pub fn toggle_pause(&mut self) {
+        // Playing again from the end starts over
+        if self.paused && self.is_at_end() {
+            self.playback.seek(0);
+        }
+        self.paused = !self.paused;
+    }
+
+    pub fn change_speed(&mut self, faster: bool) {
+        self.speed = if faster {
+            (self.speed + 1).min(SPEEDS.len() - 1)
+        } else {
+            self.speed.saturating_sub(1)
+        };
+    }
+
+    /// Jump `SEEK_FRAMES` back or forward
+    pub fn seek(&mut self, forward: bool) {
+        let frame = self.playback.frame();
+        if forward {
+            self.seek_to(frame + SEEK_FRAMES);
+        } else {
+            self.seek_to(frame.saturating_sub(SEEK_FRAMES));
+        }
+    }
+
+    /// Pause and go one frame back or forward
+    pub fn step(&mut self, forward: bool) {
+        self.paused = true;
+        if forward {
+            if !self.is_at_end() {
+                self.playback.step();
+            }
+        } else {
+            self.seek_to(self.playback.frame().saturating_sub(1));
+        }
+    }
+
+    /// Jump to `frame`, the end of the replay from the length on
+    pub fn seek_to(&mut self, frame: u64) {
+        self.partial_frame = 0.0;
+        self.playback.seek(if frame >= self.length {
+            self.end
+        } else {
+            frame
+        });
+    }
+
+    /// Whether the game is over or the replay was cut off
+    pub fn is_at_end(&self) -> bool {
+        self.playback.is_finished() || self.playback.frame() >= self.length
+    }
+
+    pub fn state(&self) -> &TetrisState {
+        self.playback.state()
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.playback.frame()
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn speed(&self) -> f64 {
+        SPEEDS[self.speed]
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+}
+
+/// Write `replay` as `<name>.replay` in the replay directory
+pub fn save_replay(replay: &Replay, name: &str) -> io::Result<PathBuf> {
+    let dir = data_dir()?.join(REPLAY_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(name).with_extension(EXTENSION);
+    fs::write(&path, replay.to_string())?;
+    Ok(path)
+}
+
+pub fn load_replay(path: &Path) -> Result<Replay, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    Ok(content.parse()?)
+}